@@ -0,0 +1,169 @@
+use rand::prelude::*;
+use rayon::prelude::*;
+use raytracer::output::{self, Format};
+use raytracer::{IntegratorKind, Pixel, Renderer, SamplerKind, Scene, SceneDescription};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Renders a scene to an image without opening a window.
+#[derive(StructOpt, Debug)]
+struct Opt {
+    /// Scene description to render, or a glTF file to render through its first camera. Defaults
+    /// to the built-in "one weekend" scene.
+    #[structopt(long, parse(from_os_str))]
+    scene: Option<PathBuf>,
+
+    /// Overrides the scene's width.
+    #[structopt(long)]
+    width: Option<usize>,
+
+    /// Overrides the scene's height.
+    #[structopt(long)]
+    height: Option<usize>,
+
+    /// Overrides the scene's samples per pixel.
+    #[structopt(long)]
+    spp: Option<usize>,
+
+    /// Overrides the scene's maximum bounce depth.
+    #[structopt(long)]
+    max_depth: Option<usize>,
+
+    /// Overrides the scene's distance a bounced ray travels before it can hit anything.
+    #[structopt(long)]
+    t_min: Option<f64>,
+
+    /// Overrides the scene's seed for the per pixel samplers.
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Overrides the scene's display gamma. Doesn't apply to hdr output.
+    #[structopt(long)]
+    gamma: Option<f64>,
+
+    /// Overrides the scene's integrator, written as in scene files: `Path`, `Recursive`,
+    /// `AmbientOcclusion(distance: 1.0)` or `Debug(Normal)`, `Debug(Albedo)` and
+    /// `Debug(Depth(far: 10.0))`.
+    #[structopt(long)]
+    integrator: Option<IntegratorKind>,
+
+    /// Overrides the scene's sampler: `Independent`, `Stratified`, `Halton`, `Sobol` or
+    /// `BlueNoise`.
+    #[structopt(long)]
+    sampler: Option<SamplerKind>,
+
+    /// Worker thread count. Defaults to the number of logical cores.
+    #[structopt(long)]
+    threads: Option<usize>,
+
+    /// Where to write the image. The extension picks the format: ppm, png or hdr.
+    #[structopt(short, long, parse(from_os_str), default_value = "out.png")]
+    output: PathBuf,
+
+    /// Writes PPM as plain text (P3) rather than binary (P6).
+    #[structopt(long)]
+    ascii: bool,
+}
+
+fn is_gltf(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => {
+            extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb")
+        }
+        None => false,
+    }
+}
+
+fn run(opt: Opt) -> Result<(), Box<dyn std::error::Error>> {
+    let mut description = match &opt.scene {
+        Some(path) if is_gltf(path) => SceneDescription::from_gltf(path)?,
+        Some(path) => SceneDescription::from_path(path)
+            .map_err(|err| format!("{}: {}", path.display(), err))?,
+        None => SceneDescription::one_weekend(&mut SmallRng::seed_from_u64(0)),
+    };
+    let mut settings = description.render;
+    if let Some(width) = opt.width {
+        settings = settings.with_resolution(width, settings.height);
+    }
+    if let Some(height) = opt.height {
+        settings = settings.with_resolution(settings.width, height);
+    }
+    if let Some(spp) = opt.spp {
+        settings = settings.with_samples_per_pixel(spp);
+    }
+    if let Some(max_depth) = opt.max_depth {
+        settings = settings.with_max_depth(max_depth);
+    }
+    if let Some(t_min) = opt.t_min {
+        settings = settings.with_t_min(t_min);
+    }
+    if let Some(seed) = opt.seed {
+        settings = settings.with_seed(seed);
+    }
+    if let Some(gamma) = opt.gamma {
+        settings = settings.with_gamma(gamma);
+    }
+    if let Some(integrator) = opt.integrator {
+        settings = settings.with_integrator(integrator);
+    }
+    if let Some(sampler) = opt.sampler {
+        settings = settings.with_sampler(sampler);
+    }
+    settings.validate()?;
+    description.render = settings;
+    let (width, height) = (settings.width, settings.height);
+    let format = match Format::from_path(&opt.output) {
+        Some(Format::Ppm) if opt.ascii => Format::PpmAscii,
+        Some(format) => format,
+        None => return Err(format!("unknown image format for {}", opt.output.display()).into()),
+    };
+
+    let dir = opt
+        .scene
+        .as_ref()
+        .and_then(|path| path.parent())
+        .unwrap_or_else(|| Path::new(""));
+    let renderer = Renderer::new(Scene::from_description(&description, dir)?)?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(opt.threads.unwrap_or_else(num_cpus::get))
+        .build()?;
+
+    let start = std::time::Instant::now();
+    let pixels = pool.install(|| {
+        (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                // images are written top down but the renderer counts rows up from the bottom
+                let x = i % width;
+                let y = height - 1 - i / width;
+                let mut sampler = settings.pixel_sampler(x, y);
+                renderer.sample(x, y, &mut sampler)
+            })
+            .collect::<Vec<_>>()
+    });
+    log::info!("rendered {}x{} in {:?}", width, height, start.elapsed());
+
+    let file = std::io::BufWriter::new(std::fs::File::create(&opt.output)?);
+    let display = || {
+        pixels
+            .iter()
+            .map(|c| Pixel::from_linear(*c, settings.gamma))
+            .collect::<Vec<_>>()
+    };
+    match format {
+        Format::Ppm => output::write_ppm(file, width, height, &display())?,
+        Format::PpmAscii => output::write_ppm_ascii(file, width, height, &display())?,
+        Format::Png => output::write_png(file, width, height, &display())?,
+        Format::Hdr => output::write_hdr(file, width, height, &pixels)?,
+    }
+    Ok(())
+}
+
+fn main() {
+    simple_logger::init().unwrap();
+    if let Err(err) = run(Opt::from_args()) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
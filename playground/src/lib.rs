@@ -1,4 +1,4 @@
-mod app;
+mod options;
 mod window;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -6,8 +6,8 @@ use glutin as winit;
 
 use graphics::texture::{Texture, TextureUpdate};
 use graphics::vertex::Vertex;
-use rand::prelude::*;
 use rayon::prelude::*;
+use raytracer::Sampler;
 use wasm_bindgen::prelude::*;
 
 #[cfg(target_arch = "wasm32")]
@@ -33,6 +33,15 @@ fn build_thread_pool() -> rayon::ThreadPool {
         .unwrap()
 }
 
+// the viewer can't start; on the web the panic hook reports it in the console
+fn exit_with(err: impl std::fmt::Display) -> ! {
+    log::error!("{}", err);
+    #[cfg(not(target_arch = "wasm32"))]
+    std::process::exit(1);
+    #[cfg(target_arch = "wasm32")]
+    panic!("{}", err);
+}
+
 #[derive(Vertex, Default, Copy, Clone, Debug)]
 #[repr(packed, C)]
 struct Vertex2D {
@@ -49,8 +58,14 @@ pub fn main() {
 
     let thread_pool = build_thread_pool();
 
-    const WIDTH: u32 = 720;
-    const HEIGHT: u32 = 480;
+    let options = options::from_env().unwrap_or_else(|err| exit_with(err));
+    let settings = options.description.render;
+    let (width, height) = (settings.width, settings.height);
+    let app = {
+        let scene = raytracer::Scene::from_description(&options.description, &options.dir)
+            .unwrap_or_else(|err| exit_with(err));
+        raytracer::Renderer::new(scene).unwrap_or_else(|err| exit_with(err))
+    };
 
     let el = winit::event_loop::EventLoop::new();
     let wb = winit::window::WindowBuilder::new()
@@ -70,27 +85,15 @@ pub fn main() {
         graphics::VertexWinding::CounterClockWise,
     ));
 
-    let app = app::App::new(WIDTH as _, HEIGHT as _);
-    let pixels = {
-        let mut pixels = (0..WIDTH * HEIGHT)
-            .map(|i| {
-                let x = (i % WIDTH) as usize;
-                let y = ((i / WIDTH) % HEIGHT) as usize;
-                (x, y)
-            })
-            .collect::<Vec<_>>();
-        let mut rng = SmallRng::seed_from_u64(0);
-        pixels.shuffle(&mut rng);
-        pixels
-    };
-    let mut pixel_data = vec![raytracer::Pixel::default(); (WIDTH * HEIGHT) as usize];
+    let mut film = raytracer::Film::new(width, height);
+    let mut pixel_data = vec![raytracer::Pixel::default(); width * height];
 
     let image = graphics::image::Image::with_data(
         &mut ctx,
         graphics::texture::TextureType::Tex2D,
         graphics::data::PixelFormat::RGB8,
-        WIDTH,
-        HEIGHT,
+        width as _,
+        height as _,
         unsafe {
             let pixels = &pixel_data;
             std::slice::from_raw_parts(
@@ -128,16 +131,24 @@ pub fn main() {
 
     let (sender, recv) = std::sync::mpsc::channel();
     thread_pool.spawn(move || {
-        pixels
-            .into_par_iter()
-            .for_each_with(sender, |sender, (x, y)| {
-                let seed = x + (WIDTH as usize) * y;
-                let mut rng = SmallRng::seed_from_u64(seed as _);
-                let pixel = app.draw(x, y, &mut rng);
-                sender
-                    .send(((x, y), pixel))
-                    .expect("failed to send, the main thread is probably dead");
-            });
+        // every pass adds one sample to every pixel so the whole image refines together
+        let settings = app.settings();
+        for pass in 0..settings.samples_per_pixel {
+            (0..height)
+                .into_par_iter()
+                .for_each_with(sender.clone(), |sender, y| {
+                    let row = (0..width)
+                        .map(|x| {
+                            let mut sampler = settings.pixel_sampler(x, y);
+                            sampler.start_sample(pass);
+                            app.trace(x, y, &mut sampler)
+                        })
+                        .collect::<Vec<_>>();
+                    sender
+                        .send((y, row))
+                        .expect("failed to send, the main thread is probably dead");
+                });
+        }
     });
 
     el.run(move |e, _, cx| {
@@ -167,13 +178,15 @@ pub fn main() {
             Event::MainEventsCleared => window.request_redraw(),
             Event::RedrawRequested(_) => {
                 let mut updated = false;
-                for ((x, y), pixel) in recv.try_iter() {
+                for (y, row) in recv.try_iter() {
                     updated = true;
-                    let i = x + WIDTH as usize * y;
-                    pixel_data[i] = pixel;
+                    for (x, radiance) in row.into_iter().enumerate() {
+                        film.add_sample(x, y, radiance);
+                    }
                 }
 
                 if updated {
+                    film.resolve(&mut pixel_data, settings.gamma);
                     ctx.set_texture_data(
                         image.get_texture_key(),
                         image.get_texture_info(),
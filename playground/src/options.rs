@@ -0,0 +1,153 @@
+//! What the viewer renders, picked at runtime.
+//!
+//! Options are `key=value` pairs: command line arguments natively, and the page's query string
+//! on the web, like `?spp=100&integrator=Debug(Normal)`. `scene` loads a scene file or glTF
+//! instead of the built-in scene, natively only since the web has no file system. The rest
+//! override its render settings the way the headless renderer's flags do: `width`, `height`,
+//! `spp`, `max_depth`, `t_min`, `seed`, `gamma`, `integrator` and `sampler`.
+
+use rand::prelude::*;
+use raytracer::{RenderSettings, SceneDescription};
+use std::path::{Path, PathBuf};
+
+pub struct Options {
+    pub description: SceneDescription,
+    /// Where the files the scene references are.
+    pub dir: PathBuf,
+}
+
+fn parse<T>(key: &str, value: &str) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| format!("invalid {} `{}`: {}", key, value, err))
+}
+
+fn with_option(settings: RenderSettings, key: &str, value: &str) -> Result<RenderSettings, String> {
+    Ok(match key {
+        "width" => settings.with_resolution(parse(key, value)?, settings.height),
+        "height" => settings.with_resolution(settings.width, parse(key, value)?),
+        "spp" => settings.with_samples_per_pixel(parse(key, value)?),
+        "max_depth" => settings.with_max_depth(parse(key, value)?),
+        "t_min" => settings.with_t_min(parse(key, value)?),
+        "seed" => settings.with_seed(parse(key, value)?),
+        "gamma" => settings.with_gamma(parse(key, value)?),
+        "integrator" => settings.with_integrator(parse(key, value)?),
+        "sampler" => settings.with_sampler(parse(key, value)?),
+        _ => return Err(format!("unknown option `{}`", key)),
+    })
+}
+
+fn is_gltf(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => {
+            extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb")
+        }
+        None => false,
+    }
+}
+
+/// The scene and settings `pairs` ask for, so long as the settings are valid.
+pub fn from_pairs(pairs: &[(String, String)]) -> Result<Options, String> {
+    let scene = pairs
+        .iter()
+        .rev()
+        .find(|(key, _)| key == "scene")
+        .map(|(_, path)| PathBuf::from(path));
+    let (mut description, dir) = match scene {
+        Some(path) => {
+            let description = if is_gltf(&path) {
+                SceneDescription::from_gltf(&path)
+            } else {
+                SceneDescription::from_path(&path)
+            };
+            let description = description.map_err(|err| format!("{}: {}", path.display(), err))?;
+            let dir = path.parent().map(Path::to_owned).unwrap_or_default();
+            (description, dir)
+        }
+        None => {
+            let mut description = SceneDescription::one_weekend(&mut SmallRng::seed_from_u64(0));
+            description.render = RenderSettings::default().with_resolution(720, 480);
+            (description, PathBuf::new())
+        }
+    };
+
+    let mut settings = description.render;
+    for (key, value) in pairs.iter().filter(|(key, _)| key != "scene") {
+        settings = with_option(settings, key, value)?;
+    }
+    settings.validate().map_err(|err| err.to_string())?;
+    description.render = settings;
+    Ok(Options { description, dir })
+}
+
+fn split_pair(pair: &str) -> Result<(String, String), String> {
+    match pair.find('=') {
+        Some(i) => Ok((pair[..i].to_owned(), pair[i + 1..].to_owned())),
+        None => Err(format!("expected `key=value`, got `{}`", pair)),
+    }
+}
+
+/// Options from the command line, like `spp=100 scene=scenes/cornell.ron`. Leading dashes are
+/// ignored, so `--spp=100` works too.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn from_env() -> Result<Options, String> {
+    let pairs = std::env::args()
+        .skip(1)
+        .map(|arg| split_pair(arg.trim_start_matches('-')))
+        .collect::<Result<Vec<_>, _>>()?;
+    from_pairs(&pairs)
+}
+
+// undoes the `%XX` escapes of a query string, and `+` for spaces
+#[cfg(target_arch = "wasm32")]
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3).and_then(|hex| {
+            std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Options from the page's query string. `scene` is an error, there are no files to load it from.
+#[cfg(target_arch = "wasm32")]
+pub fn from_env() -> Result<Options, String> {
+    let search = web_sys::window()
+        .ok_or("no window")?
+        .location()
+        .search()
+        .map_err(|_| "can't read the query string")?;
+    let pairs = search
+        .trim_start_matches('?')
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| split_pair(&percent_decode(pair)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if pairs.iter().any(|(key, _)| key == "scene") {
+        return Err("`scene` can't be loaded on the web, there's no file system".to_owned());
+    }
+    from_pairs(&pairs)
+}
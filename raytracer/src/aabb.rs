@@ -0,0 +1,83 @@
+use super::Ray;
+use cgmath::{vec3, Vector3};
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb<T> {
+    min: Vector3<T>,
+    max: Vector3<T>,
+}
+
+impl<T: cgmath::BaseFloat> Aabb<T> {
+    pub fn new(min: Vector3<T>, max: Vector3<T>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn min(&self) -> &Vector3<T> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Vector3<T> {
+        &self.max
+    }
+
+    pub fn surrounding(&self, other: &Self) -> Self {
+        Self {
+            min: vec3(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: vec3(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn centroid(&self) -> Vector3<T> {
+        (self.min + self.max) / (T::one() + T::one())
+    }
+
+    pub fn extent(&self) -> Vector3<T> {
+        self.max - self.min
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let d = self.extent();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn surface_area(&self) -> T {
+        let d = self.extent();
+        (d.x * d.y + d.x * d.z + d.y * d.z) * (T::one() + T::one())
+    }
+
+    /// Slab test. Only answers whether the ray enters the box somewhere in `t`.
+    pub fn hit(&self, r: &Ray<T>, t: Range<T>) -> bool {
+        let mut t_min = t.start;
+        let mut t_max = t.end;
+        for axis in 0..3 {
+            let inv_d = T::one() / r.direction()[axis];
+            let mut t0 = (self.min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < T::zero() {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // written so that a NaN from 0 * inf leaves the interval untouched
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
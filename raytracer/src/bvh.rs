@@ -0,0 +1,414 @@
+use super::{Aabb, HitRecord, HitTable, Ray};
+use cgmath::Vector3;
+use std::ops::Range;
+
+const MAX_LEAF_SIZE: usize = 4;
+// the traversal stack is a fixed size array so the tree can't be deeper than this
+const MAX_DEPTH: usize = 64;
+const SAH_BUCKETS: usize = 12;
+
+enum Node<T> {
+    Leaf {
+        bounds: Aabb<T>,
+        objects: Range<usize>,
+    },
+    // the first child always directly follows its parent in `nodes`
+    Interior {
+        bounds: Aabb<T>,
+        axis: usize,
+        second_child: usize,
+    },
+}
+
+impl<T> Node<T> {
+    fn bounds(&self) -> &Aabb<T> {
+        match self {
+            Node::Leaf { bounds, .. } => bounds,
+            Node::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+struct Primitive<T> {
+    index: usize,
+    bounds: Aabb<T>,
+    centroid: Vector3<T>,
+}
+
+/// A bounding volume hierarchy built with a binned surface area heuristic.
+///
+/// Bounds are taken over the shutter interval passed to `new`. Anything that can't report a
+/// bounding box is kept to the side and tested linearly.
+pub struct Bvh<T, R> {
+    objects: Vec<Box<dyn HitTable<T, R> + Send + Sync>>,
+    unbounded: Vec<Box<dyn HitTable<T, R> + Send + Sync>>,
+    nodes: Vec<Node<T>>,
+}
+
+impl<T: cgmath::BaseFloat, R> Bvh<T, R> {
+    pub fn new(list: Vec<Box<dyn HitTable<T, R> + Send + Sync>>, time: Range<T>) -> Self {
+        let mut bounded = vec![];
+        let mut unbounded = vec![];
+        let mut primitives = vec![];
+        for ht in list {
+            match ht.bounding_box(time.clone()) {
+                Some(bounds) => {
+                    primitives.push(Primitive {
+                        index: bounded.len(),
+                        bounds,
+                        centroid: bounds.centroid(),
+                    });
+                    bounded.push(Some(ht));
+                }
+                None => unbounded.push(ht),
+            }
+        }
+
+        let mut nodes = vec![];
+        if !primitives.is_empty() {
+            build(&mut nodes, &mut primitives, 0, 0);
+        }
+
+        // leaves index into the primitives in the order the build left them
+        let objects = primitives
+            .iter()
+            .map(|p| bounded[p.index].take().unwrap())
+            .collect();
+
+        Self {
+            objects,
+            unbounded,
+            nodes,
+        }
+    }
+
+    /// Calls `f` on everything `r` could hit within `t`: each object in a leaf whose bounds it
+    /// crosses, and everything unbounded.
+    pub fn for_each_crossed<F>(&self, r: &Ray<T>, t: Range<T>, mut f: F)
+    where
+        F: FnMut(&(dyn HitTable<T, R> + Send + Sync)),
+    {
+        for ht in self.unbounded.iter() {
+            f(ht.as_ref());
+        }
+
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounds().hit(r, t.clone()) {
+                match node {
+                    Node::Leaf { objects, .. } => {
+                        for ht in self.objects[objects.clone()].iter() {
+                            f(ht.as_ref());
+                        }
+                    }
+                    Node::Interior { second_child, .. } => {
+                        stack[stack_len] = *second_child;
+                        stack_len += 1;
+                        current += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+    }
+}
+
+fn surrounding<'a, T, I>(boxes: I) -> Option<Aabb<T>>
+where
+    T: cgmath::BaseFloat + 'a,
+    I: IntoIterator<Item = &'a Aabb<T>>,
+{
+    boxes.into_iter().fold(None, |acc, b| match acc {
+        None => Some(*b),
+        Some(acc) => Some(acc.surrounding(b)),
+    })
+}
+
+fn build<T: cgmath::BaseFloat>(
+    nodes: &mut Vec<Node<T>>,
+    primitives: &mut [Primitive<T>],
+    offset: usize,
+    depth: usize,
+) -> usize {
+    let index = nodes.len();
+    let bounds = surrounding(primitives.iter().map(|p| &p.bounds)).unwrap();
+    let leaf = Node::Leaf {
+        bounds,
+        objects: offset..offset + primitives.len(),
+    };
+
+    if primitives.len() <= MAX_LEAF_SIZE || depth >= MAX_DEPTH {
+        nodes.push(leaf);
+        return index;
+    }
+
+    let centroid_bounds = primitives.iter().skip(1).fold(
+        Aabb::new(primitives[0].centroid, primitives[0].centroid),
+        |acc, p| acc.surrounding(&Aabb::new(p.centroid, p.centroid)),
+    );
+    let axis = centroid_bounds.longest_axis();
+    let min = centroid_bounds.min()[axis];
+    let extent = centroid_bounds.extent()[axis];
+    if extent <= T::zero() {
+        // every centroid is in the same spot so there's no way to split them
+        nodes.push(leaf);
+        return index;
+    }
+
+    let bucket_count = T::from(SAH_BUCKETS).unwrap();
+    let bucket = |p: &Primitive<T>| {
+        let b = ((p.centroid[axis] - min) / extent * bucket_count)
+            .to_usize()
+            .unwrap_or(0);
+        b.min(SAH_BUCKETS - 1)
+    };
+
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bucket_bounds: [Option<Aabb<T>>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+    for p in primitives.iter() {
+        let b = bucket(p);
+        counts[b] += 1;
+        bucket_bounds[b] = surrounding(bucket_bounds[b].iter().chain(Some(&p.bounds)));
+    }
+
+    // cost of splitting after bucket `i`, relative to the cost of a single intersection test
+    let traversal_cost = T::from(0.125).unwrap();
+    let mut best = None;
+    for i in 0..SAH_BUCKETS - 1 {
+        let below = surrounding(bucket_bounds[..=i].iter().flatten());
+        let above = surrounding(bucket_bounds[i + 1..].iter().flatten());
+        if let (Some(below), Some(above)) = (below, above) {
+            let count_below = T::from(counts[..=i].iter().sum::<usize>()).unwrap();
+            let count_above = T::from(counts[i + 1..].iter().sum::<usize>()).unwrap();
+            let cost = traversal_cost
+                + (count_below * below.surface_area() + count_above * above.surface_area())
+                    / bounds.surface_area();
+            match best {
+                Some((_, best_cost)) if best_cost <= cost => {}
+                _ => best = Some((i, cost)),
+            }
+        }
+    }
+
+    let split = match best {
+        Some((split, _)) => split,
+        None => {
+            nodes.push(leaf);
+            return index;
+        }
+    };
+
+    let mut mid = 0;
+    for i in 0..primitives.len() {
+        if bucket(&primitives[i]) <= split {
+            primitives.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    nodes.push(Node::Interior {
+        bounds,
+        axis,
+        second_child: 0,
+    });
+    let (below, above) = primitives.split_at_mut(mid);
+    build(nodes, below, offset, depth + 1);
+    let second = build(nodes, above, offset + mid, depth + 1);
+    if let Node::Interior { second_child, .. } = &mut nodes[index] {
+        *second_child = second;
+    }
+    index
+}
+
+impl<T: cgmath::BaseFloat, R> HitTable<T, R> for Bvh<T, R> {
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let mut closest_so_far = t.end;
+        let mut hit = None;
+        for ht in self.unbounded.iter() {
+            if let Some(hc) = ht.hit(rng, r, t.start..closest_so_far) {
+                closest_so_far = hc.get_t();
+                hit = Some(hc);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return hit;
+        }
+
+        let dir_is_neg = [
+            r.direction().x < T::zero(),
+            r.direction().y < T::zero(),
+            r.direction().z < T::zero(),
+        ];
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounds().hit(r, t.start..closest_so_far) {
+                match node {
+                    Node::Leaf { objects, .. } => {
+                        for ht in self.objects[objects.clone()].iter() {
+                            if let Some(hc) = ht.hit(rng, r, t.start..closest_so_far) {
+                                closest_so_far = hc.get_t();
+                                hit = Some(hc);
+                            }
+                        }
+                    }
+                    Node::Interior {
+                        axis, second_child, ..
+                    } => {
+                        // visit the nearer child first so the far one can be culled by `closest_so_far`
+                        let (near, far) = if dir_is_neg[*axis] {
+                            (*second_child, current + 1)
+                        } else {
+                            (current + 1, *second_child)
+                        };
+                        stack[stack_len] = far;
+                        stack_len += 1;
+                        current = near;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+        hit
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|node| *node.bounds())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cuboid, HitTableList, Lambertian, Sphere, XYRect};
+    use cgmath::vec3;
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+    use std::sync::Arc as Rc;
+
+    type Object = Box<dyn HitTable<f64, SmallRng> + Send + Sync>;
+
+    // a sphere that won't report a box, so the bvh has to keep it to the side
+    struct Unbounded(Sphere<f64, SmallRng>);
+
+    impl HitTable<f64, SmallRng> for Unbounded {
+        fn hit(
+            &self,
+            rng: &mut SmallRng,
+            r: &Ray<f64>,
+            t: Range<f64>,
+        ) -> Option<HitRecord<f64, SmallRng>> {
+            self.0.hit(rng, r, t)
+        }
+
+        fn bounding_box(&self, _time: Range<f64>) -> Option<Aabb<f64>> {
+            None
+        }
+    }
+
+    fn objects(seed: u64) -> Vec<Object> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        let point = |rng: &mut SmallRng| {
+            vec3(
+                rng.gen_range(-10.0, 10.0),
+                rng.gen_range(-10.0, 10.0),
+                rng.gen_range(-10.0, 10.0),
+            )
+        };
+        let mut objects: Vec<Object> = vec![];
+        for i in 0..200 {
+            let p = point(&mut rng);
+            let size = rng.gen_range(0.1, 1.0);
+            objects.push(match i % 4 {
+                0 | 1 => Box::new(Sphere::new(p, size, material.clone())),
+                2 => Box::new(Cuboid::new(p, p + vec3(size, size, size), material.clone())),
+                _ => Box::new(XYRect::new(
+                    p.x..p.x + size,
+                    p.y..p.y + size,
+                    p.z,
+                    material.clone(),
+                )),
+            });
+        }
+        for _ in 0..3 {
+            let p = point(&mut rng);
+            objects.push(Box::new(Unbounded(Sphere::new(p, 0.5, material.clone()))));
+        }
+        objects
+    }
+
+    #[test]
+    fn hits_match_a_linear_scan() {
+        let bvh = Bvh::new(objects(1), 0.0..1.0);
+        assert_eq!(bvh.bounding_box(0.0..1.0), None);
+        let mut list = HitTableList::new();
+        for object in objects(1) {
+            list.add(object);
+        }
+
+        let mut rng = SmallRng::seed_from_u64(2);
+        let (mut hits, mut misses) = (0, 0);
+        for i in 0..20_000 {
+            let origin = vec3(
+                rng.gen_range(-15.0, 15.0),
+                rng.gen_range(-15.0, 15.0),
+                rng.gen_range(-15.0, 15.0),
+            );
+            let mut direction = vec3(
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+            );
+            // some rays run parallel to one or two of the slabs
+            match i % 4 {
+                1 => direction.x = 0.0,
+                2 => {
+                    direction.x = 0.0;
+                    direction.y = 0.0;
+                }
+                _ => {}
+            }
+            if direction == vec3(0.0, 0.0, 0.0) {
+                continue;
+            }
+            let r = Ray::new(origin, direction, 0.0);
+            let t = 0.001..f64::MAX;
+            let expected = list.hit(&mut rng, &r, t.clone()).map(|hit| hit.get_t());
+            let actual = bvh.hit(&mut rng, &r, t).map(|hit| hit.get_t());
+            assert_eq!(actual, expected, "{:?} {:?}", origin, direction);
+            if expected.is_some() {
+                hits += 1;
+            } else {
+                misses += 1;
+            }
+        }
+        assert!(hits > 1000 && misses > 1000);
+    }
+}
@@ -13,6 +13,8 @@ pub struct Camera<T> {
     #[allow(unused)]
     w: Vector3<T>,
     lens_radius: T,
+    shutter_open: T,
+    shutter_close: T,
 }
 
 impl Camera<f64> {
@@ -45,10 +47,22 @@ impl Camera<f64> {
             v,
             w,
             lens_radius: aperture / 2.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 }
 
+impl<T> Camera<T> {
+    /// Rays are cast at times spread evenly between `open` and `close`. By default the shutter
+    /// is instantaneous, at time zero.
+    pub fn with_shutter(mut self, open: T, close: T) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+}
+
 // FIXME: this shouldn't acquire it's own rng
 fn rand_in_unit_disk<T, R>(rng: &mut R) -> Vector3<T>
 where
@@ -56,16 +70,22 @@ where
     Standard: Distribution<T>,
     R: rand::Rng,
 {
+    // Shirley and Chiu's concentric mapping, which takes evenly spread points in the square to
+    // evenly spread points on the disk, where rejection sampling would skip some
     let one = T::one();
     let two = one + one;
-    loop {
-        let x = rng.gen::<T>();
-        let y = rng.gen::<T>();
-        let p = vec3(x, y, T::zero()) * two - vec3(one, one, T::zero());
-        if p.magnitude2() < one {
-            return p;
-        }
+    let x = rng.gen::<T>() * two - one;
+    let y = rng.gen::<T>() * two - one;
+    if x == T::zero() && y == T::zero() {
+        return vec3(T::zero(), T::zero(), T::zero());
     }
+    let quarter_pi = T::from(std::f64::consts::FRAC_PI_4).unwrap();
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, quarter_pi * (y / x))
+    } else {
+        (y, quarter_pi * (two - x / y))
+    };
+    vec3(r * theta.cos(), r * theta.sin(), T::zero())
 }
 
 impl<T> Camera<T>
@@ -81,9 +101,11 @@ where
 
         let rd = rand_in_unit_disk(rng).mul_element_wise(self.lens_radius);
         let offset = self.u.mul_element_wise(rd.x) + self.v.mul_element_wise(rd.y);
+        let time = self.shutter_open + rng.gen::<T>() * (self.shutter_close - self.shutter_open);
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
+            time,
         )
     }
 }
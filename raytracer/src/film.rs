@@ -0,0 +1,70 @@
+use super::spectrum::Radiance;
+use super::Pixel;
+use cgmath::{vec3, Vector3};
+
+/// Accumulates linear radiance per pixel so an image can be refined a sample at a time. Light
+/// found at single wavelengths is summed as XYZ, and only turned into RGB when it's read.
+///
+/// Pixels are indexed like the renderer, with `y` counting up from the bottom.
+pub struct Film {
+    width: usize,
+    height: usize,
+    sums: Vec<Radiance>,
+    counts: Vec<u32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sums: vec![Radiance::default(); width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add_sample(&mut self, x: usize, y: usize, radiance: Radiance) {
+        let i = x + self.width * y;
+        self.sums[i] += radiance;
+        self.counts[i] += 1;
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.counts[x + self.width * y]
+    }
+
+    /// The mean of the samples so far. Black if there aren't any.
+    pub fn get(&self, x: usize, y: usize) -> Vector3<f64> {
+        let i = x + self.width * y;
+        if self.counts[i] == 0 {
+            vec3(0.0, 0.0, 0.0)
+        } else {
+            self.sums[i].to_rgb() / self.counts[i] as f64
+        }
+    }
+
+    /// Converts the current estimate of every pixel for display.
+    pub fn resolve(&self, pixels: &mut [Pixel], gamma: f64) {
+        assert_eq!(pixels.len(), self.sums.len(), "pixel buffer size mismatch");
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = Pixel::from_linear(self.get(i % self.width, i / self.width), gamma);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for sum in self.sums.iter_mut() {
+            *sum = Radiance::default();
+        }
+        for count in self.counts.iter_mut() {
+            *count = 0;
+        }
+    }
+}
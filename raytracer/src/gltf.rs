@@ -0,0 +1,447 @@
+//! glTF 2.0 import, from `.gltf` files with their buffers and images alongside or from
+//! self-contained `.glb`s.
+//!
+//! Each primitive becomes a `TriangleMesh` with a `Principled` material. Meshes are built once
+//! and placed by every node that uses them through a `Transform`. Normal and occlusion maps,
+//! vertex colors, transparency, skins and animation are ignored.
+
+use super::scene::CameraDescription;
+use super::texture::srgb_to_linear;
+use super::{
+    Bvh, Filter, HitTable, ImageTexture, Material, Principled, SolidColor, Texture, Transform,
+    TriangleMesh, Wrap,
+};
+use ::gltf::camera::Projection;
+use ::gltf::image::Format;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::{MagFilter, WrappingMode};
+use cgmath::{vec2, vec3, vec4, ElementWise, Matrix4, SquareMatrix, Vector3};
+use std::collections::hash_map::{Entry, HashMap};
+use std::path::Path;
+use std::sync::Arc as Rc;
+
+type Object<R> = Rc<dyn HitTable<f64, R> + Send + Sync>;
+type MaterialRef<R> = Rc<dyn Material<f64, R> + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+    Gltf(::gltf::Error),
+    /// A primitive indexes past the end of its vertices, or its attributes differ in length.
+    InvalidMesh {
+        mesh: usize,
+    },
+    NoCamera,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Gltf(err) => write!(f, "{}", err),
+            Error::InvalidMesh { mesh } => write!(f, "mesh {} has mismatched vertex data", mesh),
+            Error::NoCamera => write!(f, "no perspective camera in the scene"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Gltf(err) => Some(err),
+            Error::InvalidMesh { .. } | Error::NoCamera => None,
+        }
+    }
+}
+
+impl From<::gltf::Error> for Error {
+    fn from(err: ::gltf::Error) -> Self {
+        Error::Gltf(err)
+    }
+}
+
+/// The default scene of a glTF file, in world space.
+pub struct Gltf<R> {
+    /// One per node with a mesh.
+    pub objects: Vec<Box<dyn HitTable<f64, R> + Send + Sync>>,
+    /// The primitives among them with emissive materials, placed the same way, to sample as
+    /// lights.
+    pub lights: Vec<Box<dyn HitTable<f64, R> + Send + Sync>>,
+    /// Every perspective camera, in the order the nodes were visited.
+    pub cameras: Vec<CameraDescription>,
+}
+
+// calls `f` with every node of the default scene, or the first one, and its world transform
+fn walk<F>(document: &::gltf::Document, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&::gltf::Node, &Matrix4<f64>) -> Result<(), Error>,
+{
+    fn visit<F>(node: ::gltf::Node, parent: &Matrix4<f64>, f: &mut F) -> Result<(), Error>
+    where
+        F: FnMut(&::gltf::Node, &Matrix4<f64>) -> Result<(), Error>,
+    {
+        // column major, like cgmath
+        let local = Matrix4::from(node.transform().matrix()).cast().unwrap();
+        let world = parent * local;
+        f(&node, &world)?;
+        for child in node.children() {
+            visit(child, &world, f)?;
+        }
+        Ok(())
+    }
+
+    let scene = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => scene,
+        None => return Ok(()),
+    };
+    for node in scene.nodes() {
+        visit(node, &Matrix4::identity(), &mut f)?;
+    }
+    Ok(())
+}
+
+// glTF cameras look down -z with +y up
+fn camera(camera: &::gltf::Camera, world: &Matrix4<f64>) -> Option<CameraDescription> {
+    let perspective = match camera.projection() {
+        Projection::Perspective(perspective) => perspective,
+        // `Camera` only does perspective
+        Projection::Orthographic(_) => return None,
+    };
+    Some(CameraDescription {
+        origin: (world * vec4(0.0, 0.0, 0.0, 1.0)).truncate().into(),
+        look_at: (world * vec4(0.0, 0.0, -1.0, 1.0)).truncate().into(),
+        up: (world * vec4(0.0, 1.0, 0.0, 0.0)).truncate().into(),
+        v_fov: f64::from(perspective.yfov()).to_degrees(),
+        aspect: perspective.aspect_ratio().map(f64::from),
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    })
+}
+
+// the image behind `texture` as linear colors, scaled by `factor`
+fn texture(
+    texture: &::gltf::Texture,
+    images: &[::gltf::image::Data],
+    srgb: bool,
+    factor: Vector3<f64>,
+) -> ImageTexture<f64> {
+    let image = &images[texture.source().index()];
+    let (channels, wide) = match image.format {
+        Format::R8 => (1, false),
+        Format::R8G8 => (2, false),
+        Format::R8G8B8 | Format::B8G8R8 => (3, false),
+        Format::R8G8B8A8 | Format::B8G8R8A8 => (4, false),
+        Format::R16 => (1, true),
+        Format::R16G16 => (2, true),
+        Format::R16G16B16 => (3, true),
+        Format::R16G16B16A16 => (4, true),
+    };
+    let bgr = matches!(image.format, Format::B8G8R8 | Format::B8G8R8A8);
+    let channel = |texel: &[u8], i: usize| {
+        if wide {
+            f64::from(u16::from_ne_bytes([texel[2 * i], texel[2 * i + 1]])) / 65535.0
+        } else {
+            f64::from(texel[i]) / 255.0
+        }
+    };
+    let stride = if wide { channels * 2 } else { channels };
+    let texels = image
+        .pixels
+        .chunks_exact(stride)
+        .map(|texel| {
+            // one and two channel images are grey, with or without alpha
+            let c = if channels < 3 {
+                let l = channel(texel, 0);
+                vec3(l, l, l)
+            } else if bgr {
+                vec3(channel(texel, 2), channel(texel, 1), channel(texel, 0))
+            } else {
+                vec3(channel(texel, 0), channel(texel, 1), channel(texel, 2))
+            };
+            let c = if srgb { c.map(srgb_to_linear) } else { c };
+            c.mul_element_wise(factor)
+        })
+        .collect();
+
+    let sampler = texture.sampler();
+    let filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => Filter::Nearest,
+        _ => Filter::Bilinear,
+    };
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => Wrap::Clamp,
+        WrappingMode::MirroredRepeat => Wrap::Mirror,
+        WrappingMode::Repeat => Wrap::Repeat,
+    };
+    ImageTexture::new(image.width as usize, image.height as usize, texels)
+        .with_filter(filter)
+        .with_wraps(wrap(sampler.wrap_s()), wrap(sampler.wrap_t()))
+}
+
+fn material<R>(material: &::gltf::Material, images: &[::gltf::image::Data]) -> MaterialRef<R>
+where
+    R: rand::Rng,
+{
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let base_color_factor = vec3(f64::from(r), f64::from(g), f64::from(b));
+    let base_color: Rc<dyn Texture<f64> + Send + Sync> = match pbr.base_color_texture() {
+        Some(info) => Rc::new(texture(&info.texture(), images, true, base_color_factor)),
+        None => Rc::new(SolidColor::new(base_color_factor)),
+    };
+    let mut principled = Principled::textured(
+        base_color,
+        f64::from(pbr.metallic_factor()),
+        f64::from(pbr.roughness_factor()),
+    );
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        let ones = vec3(1.0, 1.0, 1.0);
+        principled = principled.with_metallic_roughness(Rc::new(texture(
+            &info.texture(),
+            images,
+            false,
+            ones,
+        )));
+    }
+
+    let [r, g, b] = material.emissive_factor();
+    let emissive_factor = vec3(f64::from(r), f64::from(g), f64::from(b));
+    // the texture is scaled by the factor, which is black unless it's set, so only a factor
+    // makes the surface glow
+    if emissive_factor != vec3(0.0, 0.0, 0.0) {
+        let emission: Rc<dyn Texture<f64> + Send + Sync> = match material.emissive_texture() {
+            Some(info) => Rc::new(texture(&info.texture(), images, true, emissive_factor)),
+            None => Rc::new(SolidColor::new(emissive_factor)),
+        };
+        principled = principled.with_emission(emission);
+    }
+    Rc::new(principled)
+}
+
+// `None` for primitives without any triangles
+fn primitive<R>(
+    mesh: usize,
+    primitive: &::gltf::Primitive,
+    buffers: &[::gltf::buffer::Data],
+    material: MaterialRef<R>,
+) -> Result<Option<TriangleMesh<f64, R>>, Error>
+where
+    R: rand::Rng + 'static,
+{
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions = match reader.read_positions() {
+        Some(positions) => positions
+            .map(|[x, y, z]| vec3(f64::from(x), f64::from(y), f64::from(z)))
+            .collect::<Vec<_>>(),
+        None => return Ok(None),
+    };
+    let normals = reader.read_normals().map(|normals| {
+        normals
+            .map(|[x, y, z]| vec3(f64::from(x), f64::from(y), f64::from(z)))
+            .collect::<Vec<_>>()
+    });
+    // glTF's texture coordinates start at the top left
+    let uvs = reader.read_tex_coords(0).map(|uvs| {
+        uvs.into_f32()
+            .map(|[u, v]| vec2(f64::from(u), 1.0 - f64::from(v)))
+            .collect::<Vec<_>>()
+    });
+    let order = match reader.read_indices() {
+        Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+        None => (0..positions.len()).collect::<Vec<_>>(),
+    };
+
+    let indices = match primitive.mode() {
+        Mode::Triangles => order.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+        // every other triangle of a strip winds the other way
+        Mode::TriangleStrip => (0..order.len().saturating_sub(2))
+            .map(|i| {
+                if i % 2 == 0 {
+                    [order[i], order[i + 1], order[i + 2]]
+                } else {
+                    [order[i + 1], order[i], order[i + 2]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (1..order.len().saturating_sub(1))
+            .map(|i| [order[0], order[i], order[i + 1]])
+            .collect::<Vec<_>>(),
+        // points and lines have no area to hit
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => return Ok(None),
+    };
+    if indices.is_empty() {
+        return Ok(None);
+    }
+
+    let count = positions.len();
+    if indices.iter().flatten().any(|i| *i >= count)
+        || normals.as_ref().is_some_and(|n| n.len() != count)
+        || uvs.as_ref().is_some_and(|uv| uv.len() != count)
+    {
+        return Err(Error::InvalidMesh { mesh });
+    }
+    Ok(Some(TriangleMesh::new(
+        positions, normals, uvs, indices, material,
+    )))
+}
+
+// a mesh's primitives, and the ones among them that glow
+struct Mesh<R> {
+    object: Object<R>,
+    lights: Vec<Object<R>>,
+}
+
+// all of a mesh's primitives, or `None` if none of them have triangles
+fn mesh<R>(
+    mesh: &::gltf::Mesh,
+    buffers: &[::gltf::buffer::Data],
+    images: &[::gltf::image::Data],
+    materials: &mut HashMap<Option<usize>, MaterialRef<R>>,
+) -> Result<Option<Mesh<R>>, Error>
+where
+    R: rand::Rng + 'static,
+{
+    let mut parts = vec![];
+    let mut lights = vec![];
+    for p in mesh.primitives() {
+        let m = p.material();
+        let material = materials
+            .entry(m.index())
+            .or_insert_with(|| material(&m, images));
+        if let Some(part) = primitive(mesh.index(), &p, buffers, Rc::clone(material))? {
+            let part: Object<R> = Rc::new(part);
+            if material.is_emissive() {
+                lights.push(Rc::clone(&part));
+            }
+            parts.push(Box::new(part) as Box<dyn HitTable<f64, R> + Send + Sync>);
+        }
+    }
+    let object: Object<R> = match parts.len() {
+        0 => return Ok(None),
+        1 => Rc::from(parts.pop().unwrap()),
+        _ => Rc::new(Bvh::new(parts, 0.0..1.0)),
+    };
+    Ok(Some(Mesh { object, lights }))
+}
+
+/// Just the cameras of a glTF file, without reading its buffers or images.
+pub fn cameras<P: AsRef<Path>>(path: P) -> Result<Vec<CameraDescription>, Error> {
+    let document = ::gltf::Gltf::open(path)?.document;
+    let mut cameras = vec![];
+    walk(&document, |node, world| {
+        cameras.extend(node.camera().and_then(|c| camera(&c, world)));
+        Ok(())
+    })?;
+    Ok(cameras)
+}
+
+/// Loads a `.gltf` or `.glb` file from disk, resolving its buffers and images relative to it.
+pub fn load<R, P>(path: P) -> Result<Gltf<R>, Error>
+where
+    R: rand::Rng + 'static,
+    P: AsRef<Path>,
+{
+    let (document, buffers, images) = ::gltf::import(path)?;
+
+    let mut materials = HashMap::new();
+    let mut meshes: HashMap<usize, Option<Mesh<R>>> = HashMap::new();
+    let mut gltf = Gltf {
+        objects: vec![],
+        lights: vec![],
+        cameras: vec![],
+    };
+    walk(&document, |node, world| {
+        gltf.cameras
+            .extend(node.camera().and_then(|c| camera(&c, world)));
+
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => return Ok(()),
+        };
+        let mesh = match meshes.entry(mesh.index()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(self::mesh(&mesh, &buffers, &images, &mut materials)?)
+            }
+        };
+        let mesh = match mesh {
+            Some(mesh) => mesh,
+            None => return Ok(()),
+        };
+        // nodes scaled to nothing, which some files use to hide things, can't be seen anyway
+        if let Some(placed) = Transform::new(Rc::clone(&mesh.object), *world) {
+            gltf.objects.push(Box::new(placed));
+            for light in mesh.lights.iter() {
+                let placed = Transform::new(Rc::clone(light), *world).expect("inverted above");
+                gltf.lights.push(Box::new(placed));
+            }
+        }
+        Ok(())
+    })?;
+    Ok(gltf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ray, SceneDescription};
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+
+    // one glowing triangle, placed twice: doubled in size 5 down -z, and scaled to nothing. the
+    // camera sits at (0, 1, 3)
+    const TRIANGLE: &str = r#"{
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0, 2, 3]}],
+        "nodes": [
+            {"translation": [0, 0, -5], "children": [1]},
+            {"scale": [2, 2, 2], "mesh": 0},
+            {"scale": [0, 0, 0], "mesh": 0},
+            {"translation": [0, 1, 3], "camera": 0}
+        ],
+        "cameras": [{"type": "perspective",
+                     "perspective": {"yfov": 0.8, "aspectRatio": 2.0, "znear": 0.1}}],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+        "materials": [{"emissiveFactor": [1, 1, 1]}],
+        "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0]}],
+        "bufferViews": [{"buffer": 0, "byteLength": 36}],
+        "buffers": [{"byteLength": 36,
+                     "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}]
+    }"#;
+
+    #[test]
+    fn nodes_and_cameras_are_placed() {
+        let path = std::env::temp_dir().join(format!("raytracer-{}.gltf", std::process::id()));
+        std::fs::write(&path, TRIANGLE).unwrap();
+        let gltf = load::<SmallRng, _>(&path);
+        let description = SceneDescription::from_gltf(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // the zero scale node is skipped rather than failing to invert
+        let gltf = gltf.unwrap();
+        assert_eq!(gltf.objects.len(), 1);
+        assert_eq!(gltf.lights.len(), 1);
+        // only hit because the triangle is twice its size
+        let mut rng = SmallRng::seed_from_u64(0);
+        let ray = Ray::new(vec3(1.5, 0.25, 0.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = gltf.objects[0]
+            .hit(&mut rng, &ray, 0.001..f64::MAX)
+            .unwrap();
+        assert!((hit.get_t() - 5.0).abs() < 1e-9);
+
+        let camera = &gltf.cameras[0];
+        assert_eq!(camera.origin, [0.0, 1.0, 3.0]);
+        assert_eq!(camera.look_at, [0.0, 1.0, 2.0]);
+        assert_eq!(camera.aspect, Some(2.0));
+        // the resolution takes the camera's aspect, which the camera then follows
+        let description = description.unwrap();
+        let render = &description.render;
+        assert_eq!(render.width, 2 * render.height);
+        assert_eq!(description.camera.aspect, None);
+    }
+}
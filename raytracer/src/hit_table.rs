@@ -1,27 +1,33 @@
 extern crate cgmath;
 
-use super::Material;
-use cgmath::Vector3;
+use super::{Aabb, Material};
+use cgmath::{vec3, InnerSpace, Vector2, Vector3};
 use std::sync::Arc as Rc;
 
 pub struct HitRecord<T, R> {
     t: T,
     p: Vector3<T>,
     normal: Vector3<T>,
+    uv: Vector2<T>,
+    front_face: bool,
     material: Rc<dyn super::Material<T, R> + Send + Sync>,
 }
 
 impl<T: std::marker::Copy, R> HitRecord<T, R> {
+    /// Takes `normal` as is, as if the ray hit the front. See `set_face_normal`.
     pub fn new(
         t: T,
         p: Vector3<T>,
         normal: Vector3<T>,
+        uv: Vector2<T>,
         material: Rc<dyn super::Material<T, R> + Send + Sync>,
     ) -> Self {
         Self {
             t,
             p,
             normal,
+            uv,
+            front_face: true,
             material,
         }
     }
@@ -50,13 +56,99 @@ impl<T: std::marker::Copy, R> HitRecord<T, R> {
         self.normal = normal;
     }
 
+    /// Surface coordinates, for texturing.
+    pub fn get_uv(&self) -> &Vector2<T> {
+        &self.uv
+    }
+
+    pub fn set_uv(&mut self, uv: Vector2<T>) {
+        self.uv = uv;
+    }
+
+    /// Whether the ray hit the outside of the surface.
+    pub fn get_front_face(&self) -> bool {
+        self.front_face
+    }
+
     pub fn get_material(&self) -> &Rc<dyn Material<T, R> + Send + Sync> {
         &self.material
     }
 }
 
+impl<T: cgmath::BaseFloat, R> HitRecord<T, R> {
+    /// Points the normal back against `r`, remembering which side of the surface was hit.
+    /// `outward_normal` should be unit length.
+    pub fn set_face_normal(&mut self, r: &super::ray::Ray<T>, outward_normal: Vector3<T>) {
+        self.front_face = r.direction().dot(outward_normal) < T::zero();
+        self.normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+    }
+}
+
 pub trait HitTable<T, R> {
-    fn hit(&self, r: &super::ray::Ray<T>, t: std::ops::Range<T>) -> Option<HitRecord<T, R>>;
+    fn hit(
+        &self,
+        rng: &mut R,
+        r: &super::ray::Ray<T>,
+        t: std::ops::Range<T>,
+    ) -> Option<HitRecord<T, R>>;
+
+    /// The box has to contain the object for the whole of `time`, the shutter interval.
+    /// `None` means the object is unbounded (or empty).
+    fn bounding_box(&self, time: std::ops::Range<T>) -> Option<Aabb<T>>;
+
+    /// The solid angle density of `random` picking `direction` from `origin`. Only objects that
+    /// get sampled as lights need this.
+    fn pdf_value(&self, _rng: &mut R, _origin: &Vector3<T>, _direction: &Vector3<T>) -> T
+    where
+        T: cgmath::BaseNum,
+    {
+        T::zero()
+    }
+
+    /// A direction from `origin` toward a random point on the object.
+    fn random(&self, _rng: &mut R, _origin: &Vector3<T>) -> Vector3<T>
+    where
+        T: cgmath::BaseNum,
+    {
+        vec3(T::one(), T::zero(), T::zero())
+    }
+}
+
+// shared objects, so the same object can be both in the world and among its lights
+impl<T, R, H> HitTable<T, R> for Rc<H>
+where
+    H: HitTable<T, R> + ?Sized,
+{
+    fn hit(
+        &self,
+        rng: &mut R,
+        r: &super::ray::Ray<T>,
+        t: std::ops::Range<T>,
+    ) -> Option<HitRecord<T, R>> {
+        (**self).hit(rng, r, t)
+    }
+
+    fn bounding_box(&self, time: std::ops::Range<T>) -> Option<Aabb<T>> {
+        (**self).bounding_box(time)
+    }
+
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T
+    where
+        T: cgmath::BaseNum,
+    {
+        (**self).pdf_value(rng, origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T>
+    where
+        T: cgmath::BaseNum,
+    {
+        (**self).random(rng, origin)
+    }
 }
 
 pub struct HitTableList<T, R> {
@@ -68,21 +160,130 @@ impl<T, R> HitTableList<T, R> {
         HitTableList { list: vec![] }
     }
 
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
     pub fn add(&mut self, ht: Box<dyn HitTable<T, R> + Send + Sync>) {
         self.list.push(ht)
     }
+
+    pub fn into_vec(self) -> Vec<Box<dyn HitTable<T, R> + Send + Sync>> {
+        self.list
+    }
+}
+
+impl<T, R> Default for HitTableList<T, R> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T: cgmath::BaseNum, R> HitTable<T, R> for HitTableList<T, R> {
-    fn hit(&self, r: &super::ray::Ray<T>, t: std::ops::Range<T>) -> Option<HitRecord<T, R>> {
+impl<T: cgmath::BaseFloat, R: rand::Rng> HitTable<T, R> for HitTableList<T, R> {
+    fn hit(
+        &self,
+        rng: &mut R,
+        r: &super::ray::Ray<T>,
+        t: std::ops::Range<T>,
+    ) -> Option<HitRecord<T, R>> {
         let mut closest_so_far = t.end;
         let mut hit = None;
         for ht in self.list.iter() {
-            if let Some(hc) = ht.hit(r, t.start..closest_so_far) {
+            if let Some(hc) = ht.hit(rng, r, t.start..closest_so_far) {
                 closest_so_far = hc.t;
                 hit = Some(hc);
             }
         }
         hit
     }
+
+    fn bounding_box(&self, time: std::ops::Range<T>) -> Option<Aabb<T>> {
+        let mut boxes = self.list.iter().map(|ht| ht.bounding_box(time.clone()));
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, b| b.map(|b| acc.surrounding(&b)))
+    }
+
+    // sampling picks an object uniformly, so the density is the mean of theirs
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        if self.list.is_empty() {
+            return T::zero();
+        }
+        let sum = self.list.iter().fold(T::zero(), |acc, ht| {
+            acc + ht.pdf_value(rng, origin, direction)
+        });
+        sum / T::from(self.list.len()).unwrap()
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        if self.list.is_empty() {
+            return vec3(T::one(), T::zero(), T::zero());
+        }
+        self.list[rng.gen_range(0, self.list.len())].random(rng, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metal, Ray, Sphere, XYRect};
+    use cgmath::{vec3, InnerSpace};
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+    use std::sync::Arc as Rc;
+
+    const T_MIN: f64 = 0.001;
+
+    fn mirror() -> Rc<Metal<f64>> {
+        Rc::new(Metal::new(vec3(1.0, 1.0, 1.0), 0.0))
+    }
+
+    #[test]
+    fn normals_face_the_ray_from_either_side() {
+        let sphere: Sphere<f64, SmallRng> = Sphere::new(vec3(0.0, 0.0, 0.0), 1.0, mirror());
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let outside = Ray::new(vec3(0.0, 0.0, 3.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = sphere.hit(&mut rng, &outside, T_MIN..f64::MAX).unwrap();
+        assert!(hit.get_front_face());
+        assert!(hit.get_normal().dot(*outside.direction()) < 0.0);
+
+        let inside = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = sphere.hit(&mut rng, &inside, T_MIN..f64::MAX).unwrap();
+        assert!(!hit.get_front_face());
+        assert!(hit.get_normal().dot(*inside.direction()) < 0.0);
+        assert!((hit.get_normal() - vec3(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn mirrored_rays_leave_the_surface() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut world: HitTableList<f64, SmallRng> = HitTableList::new();
+        world.add(Box::new(Sphere::new(vec3(0.0, 0.0, 0.0), 1.0, mirror())));
+        world.add(Box::new(XYRect::new(-5.0..5.0, -5.0..5.0, -3.0, mirror())));
+
+        // off the outside of the sphere, the reflection can only reach the rect behind the camera
+        // or nothing, never the sphere again
+        for &x in &[0.0, 0.3, 0.7, 0.99] {
+            let r = Ray::new(vec3(x, 0.0, 3.0), vec3(0.0, 0.0, -1.0), 0.0);
+            let hit = world.hit(&mut rng, &r, T_MIN..f64::MAX).unwrap();
+            let (_, scattered) = hit.get_material().scatter(&mut rng, &r, &hit).unwrap();
+            assert!(scattered.direction().dot(*hit.get_normal()) > 0.0);
+            if let Some(next) = world.hit(&mut rng, &scattered, T_MIN..f64::MAX) {
+                assert!(next.get_t() > T_MIN);
+                assert!((next.get_p() - hit.get_p()).magnitude() > T_MIN);
+            }
+        }
+
+        // from inside, it crosses to the far side rather than hitting where it started
+        let r = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.2, 0.0, -1.0), 0.0);
+        let hit = world.hit(&mut rng, &r, T_MIN..f64::MAX).unwrap();
+        let (_, scattered) = hit.get_material().scatter(&mut rng, &r, &hit).unwrap();
+        let next = world.hit(&mut rng, &scattered, T_MIN..f64::MAX).unwrap();
+        assert!((next.get_p() - hit.get_p()).magnitude() > 1.0);
+        assert!(!next.get_front_face());
+    }
 }
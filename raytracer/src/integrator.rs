@@ -0,0 +1,426 @@
+//! Ways of estimating the light arriving along a camera ray.
+
+use super::spectrum::Radiance;
+use super::{CosinePdf, HitRecord, HitTable, HitTablePdf, Pdf, Ray, Scene};
+use cgmath::{vec3, ElementWise, InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+
+pub trait Integrator<R> {
+    /// The linear radiance arriving at the origin of `r`, against its direction.
+    fn radiance(&self, scene: &Scene<R>, rng: &mut R, r: &Ray<f64>) -> Radiance;
+}
+
+/// Picks an integrator, from a scene file or the command line.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub enum IntegratorKind {
+    /// `PathTracer`, with roulette from the third bounce.
+    #[default]
+    Path,
+    Recursive,
+    /// `AmbientOcclusion` out to `distance`.
+    AmbientOcclusion {
+        distance: f64,
+    },
+    Debug(DebugView),
+}
+
+impl std::str::FromStr for IntegratorKind {
+    type Err = ron::Error;
+
+    /// Parses the same syntax scene files use, like `Path` or `Debug(Depth(far: 10.0))`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ron::from_str(s)
+    }
+}
+
+impl IntegratorKind {
+    pub fn build<R>(&self) -> Box<dyn Integrator<R> + Send + Sync>
+    where
+        R: rand::Rng,
+    {
+        match *self {
+            IntegratorKind::Path => Box::new(PathTracer::new(3)),
+            IntegratorKind::Recursive => Box::new(RecursivePathTracer),
+            IntegratorKind::AmbientOcclusion { distance } => {
+                Box::new(AmbientOcclusion::new(distance))
+            }
+            IntegratorKind::Debug(view) => Box::new(DebugIntegrator::new(view)),
+        }
+    }
+}
+
+// the power heuristic, with an exponent of two
+fn mis_weight(pdf: f64, other: f64) -> f64 {
+    pdf * pdf / (pdf * pdf + other * other)
+}
+
+// most materials don't know about wavelengths, so what they scatter keeps the path's
+fn continue_path(ray: &Ray<f64>, scattered: Ray<f64>) -> Ray<f64> {
+    match (ray.wavelengths(), scattered.wavelengths()) {
+        (Some(wavelengths), None) => scattered.with_wavelengths(*wavelengths),
+        _ => scattered,
+    }
+}
+
+// the absorbing media a path is inside, innermost last. paths start outside of everything
+#[derive(Clone, Default)]
+struct Media(Vec<Vector3<f64>>);
+
+impl Media {
+    // the fraction of each channel that makes it `distance` through the innermost medium
+    fn transmittance(&self, distance: f64) -> Vector3<f64> {
+        match self.0.last() {
+            None => vec3(1.0, 1.0, 1.0),
+            Some(absorption) => {
+                absorption.map(|a| if a > 0.0 { (-a * distance).exp() } else { 1.0 })
+            }
+        }
+    }
+
+    // enters or leaves whatever is behind `hit`, if `scattered` passed through it
+    fn cross<R>(&mut self, hit: &HitRecord<f64, R>, scattered: &Ray<f64>) {
+        if let Some(absorption) = hit.get_material().absorption() {
+            // the normal faces the incoming ray, so rays that pass through head against it
+            if scattered.direction().dot(*hit.get_normal()) < 0.0 {
+                if hit.get_front_face() {
+                    self.0.push(absorption);
+                } else {
+                    self.0.pop();
+                }
+            }
+        }
+    }
+}
+
+// light reaching `hit` straight from a light, weighted against finding it by scattering
+fn sample_lights<R>(
+    scene: &Scene<R>,
+    rng: &mut R,
+    r: &Ray<f64>,
+    hit: &HitRecord<f64, R>,
+    media: &Media,
+) -> Vector3<f64>
+where
+    R: rand::Rng,
+{
+    let black = vec3(0.0, 0.0, 0.0);
+    let lights = HitTablePdf::new(&scene.lights, *hit.get_p());
+    let direction = lights.generate(rng);
+    let light_pdf = lights.value(rng, &direction);
+    if light_pdf <= 0.0 {
+        return black;
+    }
+    let (f, scatter_pdf) = match hit.get_material().bsdf(r, hit, &direction) {
+        Some((f, pdf)) if pdf > 0.0 => (f, pdf),
+        _ => return black,
+    };
+    let shadow = Ray::new(*hit.get_p(), direction, r.time());
+    match scene.world.hit(rng, &shadow, scene.render.t_min..f64::MAX) {
+        None => black,
+        Some(light) => {
+            let uv = light.get_uv();
+            let emitted = light.get_material().emitted(uv.x, uv.y, light.get_p());
+            let transmittance = media.transmittance(light.get_t() * direction.magnitude());
+            f.mul_element_wise(emitted).mul_element_wise(transmittance)
+                * mis_weight(light_pdf, scatter_pdf)
+                / light_pdf
+        }
+    }
+}
+
+/// An iterative path tracer with next event estimation: every diffuse bounce also aims a ray
+/// at the scene's lights, and the two estimates are blended by multiple importance sampling.
+///
+/// After `roulette_depth` bounces, paths carrying little light are randomly ended and the
+/// survivors brightened to make up for them.
+pub struct PathTracer {
+    roulette_depth: usize,
+}
+
+impl PathTracer {
+    pub fn new(roulette_depth: usize) -> Self {
+        Self { roulette_depth }
+    }
+}
+
+impl<R: rand::Rng> Integrator<R> for PathTracer {
+    fn radiance(&self, scene: &Scene<R>, rng: &mut R, r: &Ray<f64>) -> Radiance {
+        let mut radiance = Radiance::default();
+        let mut throughput = vec3(1.0, 1.0, 1.0);
+        let mut ray = continue_path(r, Ray::new(*r.origin(), *r.direction(), r.time()));
+        // the density the last bounce was scattered with, if it could have been light sampled
+        let mut scatter_pdf = None;
+        let mut media = Media::default();
+        for depth in 0..scene.render.max_depth {
+            let hit = match scene.world.hit(rng, &ray, scene.render.t_min..f64::MAX) {
+                None => {
+                    throughput = throughput.mul_element_wise(media.transmittance(f64::INFINITY));
+                    let sky = scene.sky.color(ray.direction());
+                    radiance.add(ray.wavelengths(), throughput.mul_element_wise(sky));
+                    break;
+                }
+                Some(hit) => hit,
+            };
+            let material = hit.get_material();
+            let distance = hit.get_t() * ray.direction().magnitude();
+            throughput = throughput.mul_element_wise(media.transmittance(distance));
+
+            let uv = hit.get_uv();
+            let emitted = material.emitted(uv.x, uv.y, hit.get_p());
+            let weight = match scatter_pdf {
+                Some(pdf) if !scene.lights.is_empty() && emitted != vec3(0.0, 0.0, 0.0) => {
+                    let lights = HitTablePdf::new(&scene.lights, *ray.origin());
+                    mis_weight(pdf, lights.value(rng, ray.direction()))
+                }
+                _ => 1.0,
+            };
+            radiance.add(
+                ray.wavelengths(),
+                throughput.mul_element_wise(emitted) * weight,
+            );
+
+            let (attenuation, scattered) = match material.scatter(rng, &ray, &hit) {
+                None => break,
+                Some(scatter) => scatter,
+            };
+            scatter_pdf = material
+                .bsdf(&ray, &hit, scattered.direction())
+                .map(|(_, pdf)| pdf);
+            if scatter_pdf.is_some() && !scene.lights.is_empty() {
+                let direct = sample_lights(scene, rng, &ray, &hit, &media);
+                radiance.add(ray.wavelengths(), throughput.mul_element_wise(direct));
+            }
+            throughput = throughput.mul_element_wise(attenuation);
+            media.cross(&hit, &scattered);
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = continue_path(&ray, scattered);
+        }
+        radiance
+    }
+}
+
+/// Follows a single scattered ray per bounce until it escapes, is absorbed or runs out of
+/// depth. Simple, but very noisy with small lights.
+pub struct RecursivePathTracer;
+
+impl RecursivePathTracer {
+    // adds the light reaching the origin of `r` to `radiance`, scaled by `throughput`, what
+    // the path so far lets through
+    fn color<R: rand::Rng>(
+        scene: &Scene<R>,
+        rng: &mut R,
+        r: &Ray<f64>,
+        depth: usize,
+        media: &Media,
+        throughput: Vector3<f64>,
+        radiance: &mut Radiance,
+    ) {
+        if depth < scene.render.max_depth {
+            match scene.world.hit(rng, r, scene.render.t_min..f64::MAX) {
+                None => {
+                    let sky = scene.sky.color(r.direction());
+                    let throughput =
+                        throughput.mul_element_wise(media.transmittance(f64::INFINITY));
+                    radiance.add(r.wavelengths(), throughput.mul_element_wise(sky));
+                }
+                Some(hit) => {
+                    let distance = hit.get_t() * r.direction().magnitude();
+                    let throughput = throughput.mul_element_wise(media.transmittance(distance));
+                    let material = hit.get_material();
+                    let emitted = material.emitted(hit.get_uv().x, hit.get_uv().y, hit.get_p());
+                    radiance.add(r.wavelengths(), throughput.mul_element_wise(emitted));
+                    if let Some((attenuation, ray)) = material.scatter(rng, r, &hit) {
+                        let mut inner = media.clone();
+                        inner.cross(&hit, &ray);
+                        let ray = continue_path(r, ray);
+                        let throughput = throughput.mul_element_wise(attenuation);
+                        Self::color(scene, rng, &ray, depth + 1, &inner, throughput, radiance);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: rand::Rng> Integrator<R> for RecursivePathTracer {
+    fn radiance(&self, scene: &Scene<R>, rng: &mut R, r: &Ray<f64>) -> Radiance {
+        let mut radiance = Radiance::default();
+        let throughput = vec3(1.0, 1.0, 1.0);
+        Self::color(
+            scene,
+            rng,
+            r,
+            0,
+            &Media::default(),
+            throughput,
+            &mut radiance,
+        );
+        radiance
+    }
+}
+
+/// How much of the hemisphere above the first hit is open out to `distance`. White where rays
+/// escape.
+pub struct AmbientOcclusion {
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+}
+
+impl<R: rand::Rng> Integrator<R> for AmbientOcclusion {
+    fn radiance(&self, scene: &Scene<R>, rng: &mut R, r: &Ray<f64>) -> Radiance {
+        let t = scene.render.t_min..f64::MAX;
+        let open = match scene.world.hit(rng, r, t) {
+            None => true,
+            Some(hit) => {
+                // cosine weighted, so the mean is the same as the diffuse light an evenly lit
+                // sky would give
+                let direction = CosinePdf::new(hit.get_normal()).generate(rng);
+                let probe = Ray::new(*hit.get_p(), direction.normalize(), r.time());
+                scene
+                    .world
+                    .hit(rng, &probe, scene.render.t_min..self.distance)
+                    .is_none()
+            }
+        };
+        let shade = if open { 1.0 } else { 0.0 };
+        Radiance::from(vec3(shade, shade, shade))
+    }
+}
+
+/// What `DebugIntegrator` shows.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum DebugView {
+    /// Shading normals, mapped from `-1..1` to `0..1`.
+    Normal,
+    /// What the first surface multiplies light by. Sampled, so glossy and glass materials show
+    /// an average.
+    Albedo,
+    /// Distance to the first hit as a fraction of `far`.
+    Depth { far: f64 },
+}
+
+/// Shows a property of the first surface each ray hits, for checking scenes and feeding
+/// denoisers. Rays that miss are black, or white for depth.
+pub struct DebugIntegrator {
+    view: DebugView,
+}
+
+impl DebugIntegrator {
+    pub fn new(view: DebugView) -> Self {
+        Self { view }
+    }
+}
+
+impl<R: rand::Rng> Integrator<R> for DebugIntegrator {
+    fn radiance(&self, scene: &Scene<R>, rng: &mut R, r: &Ray<f64>) -> Radiance {
+        let hit = scene.world.hit(rng, r, scene.render.t_min..f64::MAX);
+        let color = match (self.view, hit) {
+            (DebugView::Depth { .. }, None) => vec3(1.0, 1.0, 1.0),
+            (_, None) => vec3(0.0, 0.0, 0.0),
+            (DebugView::Normal, Some(hit)) => (hit.get_normal() + vec3(1.0, 1.0, 1.0)) * 0.5,
+            (DebugView::Albedo, Some(hit)) => {
+                let material = hit.get_material();
+                match material.scatter(rng, r, &hit) {
+                    Some((attenuation, _)) => attenuation,
+                    None => {
+                        let uv = hit.get_uv();
+                        material.emitted(uv.x, uv.y, hit.get_p())
+                    }
+                }
+            }
+            (DebugView::Depth { far }, Some(hit)) => {
+                let d = (hit.get_t() * r.direction().magnitude() / far).min(1.0);
+                vec3(d, d, d)
+            }
+        };
+        Radiance::from(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+
+    // a grey sphere under an evenly white sky, where every path that hits the sphere escapes
+    // after one bounce carrying exactly the albedo
+    fn furnace() -> Scene<SmallRng> {
+        let text = r#"(
+            camera: (origin: (0, 0, 5), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 20,
+                     aperture: 0, focus_dist: 5),
+            sky: Solid((1, 1, 1)),
+            materials: { "grey": Lambertian(albedo: (0.5, 0.5, 0.5)) },
+            objects: [Sphere(center: (0, 0, 0), radius: 1, material: "grey")],
+        )"#;
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn kinds_parse_like_scene_files() {
+        let parse = |s: &str| s.parse::<IntegratorKind>().unwrap();
+        assert_eq!(parse("Path"), IntegratorKind::Path);
+        assert_eq!(parse("Recursive"), IntegratorKind::Recursive);
+        assert_eq!(
+            parse("AmbientOcclusion(distance: 1.5)"),
+            IntegratorKind::AmbientOcclusion { distance: 1.5 }
+        );
+        assert_eq!(
+            parse("Debug(Depth(far: 10.0))"),
+            IntegratorKind::Debug(DebugView::Depth { far: 10.0 })
+        );
+        assert!("Bidirectional".parse::<IntegratorKind>().is_err());
+    }
+
+    #[test]
+    fn path_tracers_pass_the_furnace_test() {
+        let scene = furnace();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let hit = Ray::new(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.1, -1.0), 0.0);
+        let miss = Ray::new(vec3(0.0, 0.0, 5.0), vec3(0.0, 1.0, -1.0), 0.0);
+        for kind in &[IntegratorKind::Path, IntegratorKind::Recursive] {
+            let integrator = kind.build();
+            for _ in 0..64 {
+                let radiance = integrator.radiance(&scene, &mut rng, &hit).to_rgb();
+                assert!(
+                    (radiance - vec3(0.5, 0.5, 0.5)).magnitude() < 1e-9,
+                    "{:?}",
+                    kind
+                );
+                let radiance = integrator.radiance(&scene, &mut rng, &miss).to_rgb();
+                assert!(
+                    (radiance - vec3(1.0, 1.0, 1.0)).magnitude() < 1e-9,
+                    "{:?}",
+                    kind
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_lone_convex_object_is_unoccluded() {
+        let scene = furnace();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let integrator: Box<dyn Integrator<SmallRng> + Send + Sync> =
+            IntegratorKind::AmbientOcclusion { distance: 100.0 }.build();
+        let hit = Ray::new(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.1, -1.0), 0.0);
+        for _ in 0..64 {
+            assert_eq!(
+                integrator.radiance(&scene, &mut rng, &hit).to_rgb(),
+                vec3(1.0, 1.0, 1.0)
+            );
+        }
+    }
+}
@@ -1,13 +1,55 @@
+pub mod aabb;
+pub mod bvh;
 pub mod camera;
+pub mod film;
+pub mod gltf;
 pub mod hit_table;
+pub mod integrator;
 pub mod material;
+pub mod medium;
+pub mod noise;
+pub mod obj;
+pub mod onb;
+pub mod output;
+pub mod pdf;
 pub mod pixel;
+pub mod principled;
 pub mod ray;
+pub mod rect;
+pub mod render;
+pub mod sampler;
+pub mod scene;
+pub mod settings;
+pub mod spectrum;
 pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
 
+pub use aabb::Aabb;
+pub use bvh::Bvh;
 pub use camera::Camera;
+pub use film::Film;
 pub use hit_table::{HitRecord, HitTable, HitTableList};
-pub use material::{Dielectric, Lambertian, Material, Metal};
+pub use integrator::{
+    AmbientOcclusion, DebugIntegrator, DebugView, Integrator, IntegratorKind, PathTracer,
+    RecursivePathTracer,
+};
+pub use material::{Dielectric, DiffuseLight, Ior, Isotropic, Lambertian, Material, Metal};
+pub use medium::ConstantMedium;
+pub use noise::{Marble, NoiseTexture, Perlin, Turbulence, Wood};
+pub use onb::Onb;
+pub use pdf::{CosinePdf, HitTablePdf, MixturePdf, Pdf};
 pub use pixel::Pixel;
+pub use principled::Principled;
 pub use ray::Ray;
-pub use sphere::Sphere;
+pub use rect::{Cuboid, XYRect, XZRect, YZRect};
+pub use render::Renderer;
+pub use sampler::{BlueNoise, Halton, Independent, Sampler, SamplerKind, Sobol, Stratified};
+pub use scene::{Scene, SceneDescription, Sky};
+pub use settings::RenderSettings;
+pub use spectrum::{Radiance, Wavelengths};
+pub use sphere::{MovingSphere, Sphere};
+pub use texture::{Checker, Filter, ImageTexture, SolidColor, Texture, UvChecker, Wrap};
+pub use transform::{RotateY, Transform, Translate};
+pub use triangle::{Triangle, TriangleMesh};
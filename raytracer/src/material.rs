@@ -1,7 +1,10 @@
-use super::{HitRecord, Ray};
+use super::spectrum::Wavelengths;
+use super::{CosinePdf, HitRecord, Pdf, Ray, SolidColor, Texture};
 use cgmath::{vec3, InnerSpace, Vector3};
 use rand::distributions::Standard;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc as Rc;
 
 fn rand_in_unit_sphere<T, R>(rng: &mut R) -> Vector3<T>
 where
@@ -9,16 +12,15 @@ where
     Standard: Distribution<T>,
     R: rand::Rng,
 {
-    loop {
-        let x = rng.gen::<T>();
-        let y = rng.gen::<T>();
-        let z = rng.gen::<T>();
-        let one = T::one();
-        let p = vec3(x, y, z) * T::from(2.0).unwrap() - vec3(one, one, one);
-        if p.magnitude2() < one {
-            return p;
-        }
-    }
+    // a uniform direction, then a radius that puts as many points in each shell as its volume
+    // calls for. always three draws, unlike rejection sampling
+    let one = T::one();
+    let two = one + one;
+    let z = one - rng.gen::<T>() * two;
+    let phi = T::from(2.0 * std::f64::consts::PI).unwrap() * rng.gen::<T>();
+    let radius = rng.gen::<T>().cbrt();
+    let ring = (one - z * z).max(T::zero()).sqrt();
+    vec3(ring * phi.cos(), ring * phi.sin(), z) * radius
 }
 
 pub trait Material<T, R> {
@@ -28,14 +30,53 @@ pub trait Material<T, R> {
         r: &Ray<T>,
         rec: &HitRecord<T, R>,
     ) -> Option<(Vector3<T>, Ray<T>)>;
+
+    /// For materials that scatter over a spread of directions: the reflectance toward
+    /// `direction` times its cosine with the normal, and the density `scatter` picks it with.
+    ///
+    /// `None` for perfectly specular materials, which can't be aimed at lights.
+    fn bsdf(
+        &self,
+        _r: &Ray<T>,
+        _rec: &HitRecord<T, R>,
+        _direction: &Vector3<T>,
+    ) -> Option<(Vector3<T>, T)> {
+        None
+    }
+
+    /// For surfaces light can pass into: how strongly what they enclose absorbs each channel,
+    /// per unit distance. Integrators dim light crossing it by the Beer–Lambert law.
+    fn absorption(&self) -> Option<Vector3<T>> {
+        None
+    }
+
+    /// Light given off at surface coordinates `u`, `v` and point `p`. Most things don't glow.
+    fn emitted(&self, _u: T, _v: T, _p: &Vector3<T>) -> Vector3<T>
+    where
+        T: cgmath::BaseNum,
+    {
+        vec3(T::zero(), T::zero(), T::zero())
+    }
+
+    /// Whether `emitted` can be anything but black, which makes surfaces of the material worth
+    /// sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
 }
 
 pub struct Lambertian<T> {
-    albedo: Vector3<T>,
+    albedo: Rc<dyn Texture<T> + Send + Sync>,
 }
 
-impl<T> Lambertian<T> {
+impl<T: Copy + Send + Sync + 'static> Lambertian<T> {
     pub fn new(albedo: Vector3<T>) -> Self {
+        Self::textured(Rc::new(SolidColor::new(albedo)))
+    }
+}
+
+impl<T> Lambertian<T> {
+    pub fn textured(albedo: Rc<dyn Texture<T> + Send + Sync>) -> Self {
         Self { albedo }
     }
 }
@@ -49,22 +90,41 @@ where
     fn scatter(
         &self,
         rng: &mut R,
-        _r: &Ray<T>,
+        r: &Ray<T>,
         rec: &HitRecord<T, R>,
     ) -> Option<(Vector3<T>, Ray<T>)> {
-        let target = rec.get_p() + rec.get_normal() + rand_in_unit_sphere(rng);
-        let scattered = Ray::new(*rec.get_p(), target - rec.get_p());
-        Some((self.albedo, scattered))
+        let direction = CosinePdf::new(rec.get_normal()).generate(rng);
+        let scattered = Ray::new(*rec.get_p(), direction, r.time());
+        let uv = rec.get_uv();
+        Some((self.albedo.value(uv.x, uv.y, rec.get_p()), scattered))
+    }
+
+    fn bsdf(
+        &self,
+        _r: &Ray<T>,
+        rec: &HitRecord<T, R>,
+        direction: &Vector3<T>,
+    ) -> Option<(Vector3<T>, T)> {
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let pdf = (direction.normalize().dot(*rec.get_normal()) / pi).max(T::zero());
+        let uv = rec.get_uv();
+        Some((self.albedo.value(uv.x, uv.y, rec.get_p()) * pdf, pdf))
     }
 }
 
 pub struct Metal<T> {
-    albedo: Vector3<T>,
+    albedo: Rc<dyn Texture<T> + Send + Sync>,
     fuzz: T,
 }
 
-impl<T> Metal<T> {
+impl<T: Copy + Send + Sync + 'static> Metal<T> {
     pub fn new(albedo: Vector3<T>, fuzz: T) -> Self {
+        Self::textured(Rc::new(SolidColor::new(albedo)), fuzz)
+    }
+}
+
+impl<T> Metal<T> {
+    pub fn textured(albedo: Rc<dyn Texture<T> + Send + Sync>, fuzz: T) -> Self {
         Self { albedo, fuzz }
     }
 }
@@ -92,22 +152,71 @@ where
         let scattered = Ray::new(
             *rec.get_p(),
             reflected + rand_in_unit_sphere(rng) * self.fuzz,
+            r.time(),
         );
         if scattered.direction().dot(*rec.get_normal()) > T::zero() {
-            Some((self.albedo, scattered))
+            let uv = rec.get_uv();
+            Some((self.albedo.value(uv.x, uv.y, rec.get_p()), scattered))
         } else {
             None
         }
     }
 }
 
+/// How a refractive index varies with wavelength. Coefficients take wavelengths in
+/// micrometres, the way they're usually tabulated.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum Ior<T> {
+    /// The same at every wavelength.
+    Constant(T),
+    /// `a + b / λ²`. Crown glass is about `a: 1.5046, b: 0.0042`.
+    Cauchy { a: T, b: T },
+    /// `n² = 1 + Σ bᵢ λ² / (λ² - cᵢ)`. BK7 glass is `b: (1.03961212, 0.231792344, 1.01046945)`
+    /// and `c: (0.00600069867, 0.0200179144, 103.560653)`.
+    Sellmeier { b: [T; 3], c: [T; 3] },
+}
+
+impl<T: cgmath::BaseFloat> Ior<T> {
+    /// The index at `wavelength` nanometres.
+    pub fn at(&self, wavelength: T) -> T {
+        let micrometres = wavelength / T::from(1000.0).unwrap();
+        let l2 = micrometres * micrometres;
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => b
+                .iter()
+                .zip(c.iter())
+                .fold(T::one(), |n2, (b, c)| n2 + *b * l2 / (l2 - *c))
+                .sqrt(),
+        }
+    }
+}
+
 pub struct Dielectric<T> {
-    ref_idx: T,
+    ior: Ior<T>,
+    absorption: Vector3<T>,
 }
 
-impl<T> Dielectric<T> {
+impl<T: cgmath::BaseNum> Dielectric<T> {
     pub fn new(ref_idx: T) -> Self {
-        Self { ref_idx }
+        Self::dispersive(Ior::Constant(ref_idx))
+    }
+
+    /// Glass that bends each wavelength by a different amount, splitting white light into
+    /// colors.
+    pub fn dispersive(ior: Ior<T>) -> Self {
+        Self {
+            ior,
+            absorption: vec3(T::zero(), T::zero(), T::zero()),
+        }
+    }
+
+    /// Tints light passing through, more the further it goes. Each channel keeps `e^-(a d)` of
+    /// its light after a distance `d`. Clear by default.
+    pub fn with_absorption(mut self, absorption: Vector3<T>) -> Self {
+        self.absorption = absorption;
+        self
     }
 }
 
@@ -144,34 +253,145 @@ where
         r: &Ray<T>,
         rec: &HitRecord<T, R>,
     ) -> Option<(Vector3<T>, Ray<T>)> {
-        let reflected = reflect(*r.direction(), *rec.get_normal());
-        let normal = *rec.get_normal();
-
-        let (outward_normal, ni_over_nt, cosine) = if r.direction().dot(normal) > T::zero() {
-            (
-                -normal,
-                self.ref_idx,
-                self.ref_idx * r.direction().dot(normal) / r.direction().magnitude(),
-            )
-        } else {
-            (
-                *rec.get_normal(),
-                T::one() / self.ref_idx,
-                -r.direction().dot(normal) / r.direction().magnitude(),
-            )
+        let one = T::one();
+        // constant glass leaves the path's wavelengths alone. rays the renderer didn't cast
+        // pick their own at the first surface that disperses them
+        let wavelengths = match self.ior {
+            Ior::Constant(_) => None,
+            _ => Some(
+                r.wavelengths()
+                    .copied()
+                    .unwrap_or_else(|| Wavelengths::sample(rng.gen())),
+            ),
+        };
+        let ray = |direction, wavelengths: Option<Wavelengths<T>>| {
+            let ray = Ray::new(*rec.get_p(), direction, r.time());
+            match wavelengths {
+                Some(wavelengths) => ray.with_wavelengths(wavelengths),
+                None => ray,
+            }
         };
 
-        let refracted = refract(r.direction(), &outward_normal, ni_over_nt);
-        let reflect_prob = match refracted {
-            None => T::one(),
-            Some(_) => schlick(cosine, self.ref_idx),
+        // the normal always faces the incoming ray
+        let normal = *rec.get_normal();
+        let reflected = reflect(*r.direction(), normal);
+        let cos_incident = -r.direction().dot(normal) / r.direction().magnitude();
+
+        // the chance of reflecting at `wavelength`, and where it goes if it doesn't
+        let fresnel = |wavelength| {
+            let ref_idx = self.ior.at(wavelength);
+            let (ni_over_nt, cosine) = if rec.get_front_face() {
+                (one / ref_idx, cos_incident)
+            } else {
+                (ref_idx, ref_idx * cos_incident)
+            };
+            match refract(r.direction(), &normal, ni_over_nt) {
+                None => (one, None),
+                Some(refracted) => (schlick(cosine, ref_idx), Some(refracted)),
+            }
         };
 
-        let attenuation = vec3(T::one(), T::one(), T::one());
+        // constant indices don't look at the wavelength
+        let hero = wavelengths.map_or(one, |wavelengths| wavelengths.hero());
+        let (reflect_prob, refracted) = fresnel(hero);
+        let attenuation = vec3(one, one, one);
         if rng.gen::<T>() < reflect_prob {
-            Some((attenuation, Ray::new(*rec.get_p(), reflected)))
+            // every wavelength reflects the same way, just with different odds
+            let wavelengths = wavelengths.map(|w| w.reweighted(|wavelength| fresnel(wavelength).0));
+            Some((attenuation, ray(reflected, wavelengths)))
         } else {
-            Some((attenuation, Ray::new(*rec.get_p(), refracted.unwrap())))
+            // but each bends its own way, so only the hero can follow
+            let wavelengths = wavelengths.map(Wavelengths::terminate_secondary);
+            Some((attenuation, ray(refracted.unwrap(), wavelengths)))
         }
     }
+
+    fn absorption(&self) -> Option<Vector3<T>> {
+        Some(self.absorption)
+    }
+}
+
+pub struct DiffuseLight<T> {
+    emit: Rc<dyn Texture<T> + Send + Sync>,
+}
+
+impl<T: Copy + Send + Sync + 'static> DiffuseLight<T> {
+    pub fn new(emit: Vector3<T>) -> Self {
+        Self::textured(Rc::new(SolidColor::new(emit)))
+    }
+}
+
+impl<T> DiffuseLight<T> {
+    pub fn textured(emit: Rc<dyn Texture<T> + Send + Sync>) -> Self {
+        Self { emit }
+    }
+}
+
+impl<T, R> Material<T, R> for DiffuseLight<T>
+where
+    T: cgmath::BaseFloat,
+{
+    fn scatter(
+        &self,
+        _rng: &mut R,
+        _r: &Ray<T>,
+        _rec: &HitRecord<T, R>,
+    ) -> Option<(Vector3<T>, Ray<T>)> {
+        None
+    }
+
+    fn emitted(&self, u: T, v: T, p: &Vector3<T>) -> Vector3<T> {
+        self.emit.value(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+/// Scatters uniformly in every direction. The phase function of a `ConstantMedium`.
+pub struct Isotropic<T> {
+    albedo: Rc<dyn Texture<T> + Send + Sync>,
+}
+
+impl<T: Copy + Send + Sync + 'static> Isotropic<T> {
+    pub fn new(albedo: Vector3<T>) -> Self {
+        Self::textured(Rc::new(SolidColor::new(albedo)))
+    }
+}
+
+impl<T> Isotropic<T> {
+    pub fn textured(albedo: Rc<dyn Texture<T> + Send + Sync>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl<T, R> Material<T, R> for Isotropic<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn scatter(
+        &self,
+        rng: &mut R,
+        r: &Ray<T>,
+        rec: &HitRecord<T, R>,
+    ) -> Option<(Vector3<T>, Ray<T>)> {
+        let scattered = Ray::new(*rec.get_p(), rand_in_unit_sphere(rng), r.time());
+        let uv = rec.get_uv();
+        Some((self.albedo.value(uv.x, uv.y, rec.get_p()), scattered))
+    }
+
+    fn bsdf(
+        &self,
+        _r: &Ray<T>,
+        rec: &HitRecord<T, R>,
+        _direction: &Vector3<T>,
+    ) -> Option<(Vector3<T>, T)> {
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let pdf = T::one() / (T::from(4.0).unwrap() * pi);
+        let uv = rec.get_uv();
+        Some((self.albedo.value(uv.x, uv.y, rec.get_p()) * pdf, pdf))
+    }
 }
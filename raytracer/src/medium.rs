@@ -0,0 +1,132 @@
+use super::{Aabb, HitRecord, HitTable, Isotropic, Material, Ray, Texture};
+use cgmath::{vec2, vec3, InnerSpace, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+use std::ops::Range;
+use std::sync::Arc as Rc;
+
+/// Smoke, fog or mist filling a closed `boundary`, equally dense all the way through.
+///
+/// A ray passing through is scattered at a random distance, so thin media let most rays through
+/// and dense ones almost none. `density` is the chance of scattering per unit length, and has to
+/// be positive and finite.
+pub struct ConstantMedium<T, R> {
+    boundary: Rc<dyn HitTable<T, R> + Send + Sync>,
+    neg_inv_density: T,
+    phase_function: Rc<dyn Material<T, R> + Send + Sync>,
+}
+
+impl<T, R> ConstantMedium<T, R>
+where
+    T: cgmath::BaseFloat + Send + Sync + 'static,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    pub fn new(
+        boundary: Rc<dyn HitTable<T, R> + Send + Sync>,
+        density: T,
+        albedo: Vector3<T>,
+    ) -> Self {
+        Self::with_phase_function(boundary, density, Rc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn textured(
+        boundary: Rc<dyn HitTable<T, R> + Send + Sync>,
+        density: T,
+        albedo: Rc<dyn Texture<T> + Send + Sync>,
+    ) -> Self {
+        Self::with_phase_function(boundary, density, Rc::new(Isotropic::textured(albedo)))
+    }
+}
+
+impl<T: cgmath::BaseFloat, R> ConstantMedium<T, R> {
+    /// `phase_function` decides where rays go once they've been scattered.
+    pub fn with_phase_function(
+        boundary: Rc<dyn HitTable<T, R> + Send + Sync>,
+        density: T,
+        phase_function: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -T::one() / density,
+            phase_function,
+        }
+    }
+}
+
+impl<T, R> HitTable<T, R> for ConstantMedium<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        // find where the ray enters and leaves the boundary, even if it starts inside it
+        let enter = self
+            .boundary
+            .hit(rng, r, T::neg_infinity()..T::infinity())?;
+        let epsilon = T::from(1e-4).unwrap();
+        let leave = self
+            .boundary
+            .hit(rng, r, enter.get_t() + epsilon..T::infinity())?;
+
+        let t0 = enter.get_t().max(t.start).max(T::zero());
+        let t1 = leave.get_t().min(t.end);
+        if t0 >= t1 {
+            return None;
+        }
+
+        let ray_length = r.direction().magnitude();
+        let distance_inside = (t1 - t0) * ray_length;
+        // free flight distance, exponentially distributed. 1 - x keeps the log finite
+        let hit_distance = self.neg_inv_density * (T::one() - rng.gen::<T>()).ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t0 + hit_distance / ray_length;
+        // the normal and surface coordinates are meaningless inside a volume
+        Some(HitRecord::new(
+            t,
+            r.point_at_parameter(t),
+            vec3(T::one(), T::zero(), T::zero()),
+            vec2(T::zero(), T::zero()),
+            Rc::clone(&self.phase_function),
+        ))
+    }
+
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        self.boundary.bounding_box(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lambertian, Sphere};
+    use rand::rngs::SmallRng;
+
+    #[test]
+    fn scattering_distances_are_exponential() {
+        let density = 2.0;
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        let boundary = Rc::new(Sphere::new(vec3(0.0, 0.0, 0.0), 1000.0, material));
+        let medium: ConstantMedium<f64, SmallRng> =
+            ConstantMedium::new(boundary, density, vec3(1.0, 1.0, 1.0));
+        let mut rng = SmallRng::seed_from_u64(0);
+        // starting inside, with a direction that isn't unit length
+        let r = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 2.0, 0.0), 0.0);
+
+        let n = 100_000;
+        let distances = (0..n)
+            .map(|_| medium.hit(&mut rng, &r, 0.0..f64::MAX).unwrap().get_p().y)
+            .collect::<Vec<_>>();
+        let mean = distances.iter().sum::<f64>() / n as f64;
+        assert!((mean - 1.0 / density).abs() < 0.01, "mean {}", mean);
+        for &x in &[0.25, 0.5, 1.0, 2.0] {
+            let beyond = distances.iter().filter(|&&d| d > x).count() as f64 / n as f64;
+            let expected = (-density * x).exp();
+            assert!((beyond - expected).abs() < 0.01, "{} beyond {}", beyond, x);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Procedural noise and the textures built on it.
+//!
+//! Noise is generated from a seed with its own tiny generator rather than `SmallRng`, whose
+//! algorithm differs between 32 and 64 bit targets, so the same seed gives the same pattern in
+//! native and wasm builds.
+
+use super::Texture;
+use cgmath::{vec3, InnerSpace, Vector3};
+use std::sync::Arc as Rc;
+
+const POINT_COUNT: usize = 256;
+
+// splitmix64
+fn next_u64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// uniform in [-1, 1)
+fn next_signed(state: &mut u64) -> f64 {
+    (next_u64(state) >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+}
+
+fn permutation(state: &mut u64) -> Vec<usize> {
+    let mut p = (0..POINT_COUNT).collect::<Vec<_>>();
+    for i in (1..POINT_COUNT).rev() {
+        let j = (next_u64(state) % (i as u64 + 1)) as usize;
+        p.swap(i, j);
+    }
+    p
+}
+
+/// Perlin gradient noise.
+pub struct Perlin<T> {
+    gradients: Vec<Vector3<T>>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl<T: cgmath::BaseFloat> Perlin<T> {
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                // rejection sampling keeps the directions uniform
+                let v = vec3(
+                    next_signed(&mut state),
+                    next_signed(&mut state),
+                    next_signed(&mut state),
+                );
+                let len2 = v.magnitude2();
+                if len2 > 1e-6 && len2 <= 1.0 {
+                    break v.normalize().cast::<T>().unwrap();
+                }
+            })
+            .collect();
+        Self {
+            gradients,
+            perm_x: permutation(&mut state),
+            perm_y: permutation(&mut state),
+            perm_z: permutation(&mut state),
+        }
+    }
+
+    /// Smoothly varying noise in roughly `[-1, 1]`, with features about a unit apart.
+    pub fn noise(&self, p: &Vector3<T>) -> T {
+        let floor = vec3(p.x.floor(), p.y.floor(), p.z.floor());
+        let f = p - floor;
+        let (i, j, k) = (
+            floor.x.to_i64().unwrap_or(0),
+            floor.y.to_i64().unwrap_or(0),
+            floor.z.to_i64().unwrap_or(0),
+        );
+        let mask = POINT_COUNT as i64 - 1;
+
+        let mut c = [[[vec3(T::zero(), T::zero(), T::zero()); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, g) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i64) & mask) as usize]
+                        ^ self.perm_y[((j + dj as i64) & mask) as usize]
+                        ^ self.perm_z[((k + dk as i64) & mask) as usize];
+                    *g = self.gradients[index];
+                }
+            }
+        }
+
+        // hermite smoothing hides the lattice
+        let three = T::from(3.0).unwrap();
+        let two = T::from(2.0).unwrap();
+        let smooth = |t: T| t * t * (three - two * t);
+        let (u, v, w) = (smooth(f.x), smooth(f.y), smooth(f.z));
+
+        let mut sum = T::zero();
+        for (di, plane) in c.iter().enumerate() {
+            for (dj, row) in plane.iter().enumerate() {
+                for (dk, g) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (
+                        T::from(di).unwrap(),
+                        T::from(dj).unwrap(),
+                        T::from(dk).unwrap(),
+                    );
+                    let weight = vec3(f.x - fi, f.y - fj, f.z - fk);
+                    sum += (fi * u + (T::one() - fi) * (T::one() - u))
+                        * (fj * v + (T::one() - fj) * (T::one() - v))
+                        * (fk * w + (T::one() - fk) * (T::one() - w))
+                        * g.dot(weight);
+                }
+            }
+        }
+        sum
+    }
+
+    /// Sums `octaves` layers of noise, each twice the frequency and half the weight of the last.
+    /// Always non-negative.
+    pub fn turbulence(&self, p: &Vector3<T>, octaves: usize) -> T {
+        let mut sum = T::zero();
+        let mut p = *p;
+        let mut weight = T::one();
+        let two = T::from(2.0).unwrap();
+        for _ in 0..octaves {
+            sum += weight * self.noise(&p);
+            weight /= two;
+            p *= two;
+        }
+        sum.abs()
+    }
+}
+
+/// Grey noise, brightest where the noise is highest.
+pub struct NoiseTexture<T> {
+    perlin: Rc<Perlin<T>>,
+    scale: T,
+}
+
+impl<T> NoiseTexture<T> {
+    pub fn new(perlin: Rc<Perlin<T>>, scale: T) -> Self {
+        Self { perlin, scale }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for NoiseTexture<T> {
+    fn value(&self, _u: T, _v: T, p: &Vector3<T>) -> Vector3<T> {
+        let half = T::from(0.5).unwrap();
+        let n = half * (T::one() + self.perlin.noise(&(p * self.scale)));
+        vec3(n, n, n)
+    }
+}
+
+/// Grey turbulence, like a camouflage net.
+pub struct Turbulence<T> {
+    perlin: Rc<Perlin<T>>,
+    scale: T,
+    octaves: usize,
+}
+
+impl<T> Turbulence<T> {
+    pub fn new(perlin: Rc<Perlin<T>>, scale: T, octaves: usize) -> Self {
+        Self {
+            perlin,
+            scale,
+            octaves,
+        }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for Turbulence<T> {
+    fn value(&self, _u: T, _v: T, p: &Vector3<T>) -> Vector3<T> {
+        let n = self
+            .perlin
+            .turbulence(&(p * self.scale), self.octaves)
+            .min(T::one());
+        vec3(n, n, n)
+    }
+}
+
+/// Veins of `dark` through `light`, running along the z axis and bent by turbulence.
+pub struct Marble<T> {
+    perlin: Rc<Perlin<T>>,
+    light: Vector3<T>,
+    dark: Vector3<T>,
+    scale: T,
+    octaves: usize,
+}
+
+impl<T> Marble<T> {
+    pub fn new(
+        perlin: Rc<Perlin<T>>,
+        light: Vector3<T>,
+        dark: Vector3<T>,
+        scale: T,
+        octaves: usize,
+    ) -> Self {
+        Self {
+            perlin,
+            light,
+            dark,
+            scale,
+            octaves,
+        }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for Marble<T> {
+    fn value(&self, _u: T, _v: T, p: &Vector3<T>) -> Vector3<T> {
+        let half = T::from(0.5).unwrap();
+        let ten = T::from(10.0).unwrap();
+        let phase = self.scale * p.z + ten * self.perlin.turbulence(p, self.octaves);
+        let t = half * (T::one() + phase.sin());
+        self.dark * (T::one() - t) + self.light * t
+    }
+}
+
+/// Growth rings around the y axis, `scale` to a unit, wobbled by turbulence.
+pub struct Wood<T> {
+    perlin: Rc<Perlin<T>>,
+    light: Vector3<T>,
+    dark: Vector3<T>,
+    scale: T,
+    octaves: usize,
+}
+
+impl<T> Wood<T> {
+    pub fn new(
+        perlin: Rc<Perlin<T>>,
+        light: Vector3<T>,
+        dark: Vector3<T>,
+        scale: T,
+        octaves: usize,
+    ) -> Self {
+        Self {
+            perlin,
+            light,
+            dark,
+            scale,
+            octaves,
+        }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for Wood<T> {
+    fn value(&self, _u: T, _v: T, p: &Vector3<T>) -> Vector3<T> {
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+        let rings = self.scale * radius + self.perlin.turbulence(p, self.octaves);
+        // sharpen the rings so the dark grain is thinner than the light wood between
+        let t = (rings - rings.floor()).powi(3);
+        self.light * (T::one() - t) + self.dark * t
+    }
+}
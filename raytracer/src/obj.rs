@@ -0,0 +1,437 @@
+//! Wavefront `.obj` and `.mtl` loading.
+//!
+//! Faces are grouped by material into one `TriangleMesh` each. Polygons are triangulated as fans.
+
+use super::{Dielectric, DiffuseLight, Lambertian, Material, Metal, TriangleMesh};
+use cgmath::{vec2, vec3, Vector2, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc as Rc;
+
+type MaterialRef<T, R> = Rc<dyn Material<T, R> + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+    Mtl { file: String, error: Box<Error> },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Error::Mtl { file, error } => write!(f, "{}: {}", file, error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse { .. } => None,
+            Error::Mtl { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+fn parse_error<S: Into<String>>(line: usize, message: S) -> Error {
+    Error::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+// yields (line number, keyword, arguments) for every non-empty line
+fn statements(src: &str) -> impl Iterator<Item = (usize, &str, Vec<&str>)> {
+    src.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        words
+            .next()
+            .map(|keyword| (i + 1, keyword, words.collect()))
+    })
+}
+
+fn floats(line: usize, args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, Error> {
+    if args.len() < min || args.len() > max {
+        return Err(parse_error(
+            line,
+            format!(
+                "expected between {} and {} numbers, found {}",
+                min,
+                max,
+                args.len()
+            ),
+        ));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| parse_error(line, format!("invalid number `{}`", arg)))
+        })
+        .collect()
+}
+
+fn color(line: usize, args: &[&str]) -> Result<[f64; 3], Error> {
+    let c = floats(line, args, 1, 3)?;
+    // a single value is a grey
+    Ok(match c.as_slice() {
+        [v] => [*v, *v, *v],
+        [r, g, b] => [*r, *g, *b],
+        _ => return Err(parse_error(line, "expected 1 or 3 color components")),
+    })
+}
+
+fn to_vec3<T: cgmath::BaseFloat>(c: [f64; 3]) -> Vector3<T> {
+    vec3(
+        T::from(c[0]).unwrap(),
+        T::from(c[1]).unwrap(),
+        T::from(c[2]).unwrap(),
+    )
+}
+
+struct MtlDescription {
+    kd: [f64; 3],
+    ks: [f64; 3],
+    ke: [f64; 3],
+    ns: f64,
+    ni: f64,
+    d: f64,
+    illum: Option<u32>,
+}
+
+impl Default for MtlDescription {
+    fn default() -> Self {
+        Self {
+            kd: [0.8, 0.8, 0.8],
+            ks: [0.0, 0.0, 0.0],
+            ke: [0.0, 0.0, 0.0],
+            ns: 0.0,
+            ni: 1.0,
+            d: 1.0,
+            illum: None,
+        }
+    }
+}
+
+impl MtlDescription {
+    fn build<T, R>(&self) -> MaterialRef<T, R>
+    where
+        T: cgmath::BaseFloat + Send + Sync + 'static,
+        Standard: Distribution<T>,
+        R: rand::Rng + 'static,
+    {
+        let max = |c: [f64; 3]| c[0].max(c[1]).max(c[2]);
+        let transparent =
+            self.d < 1.0 || matches!(self.illum, Some(4) | Some(6) | Some(7) | Some(9));
+        if max(self.ke) > 0.0 {
+            Rc::new(DiffuseLight::new(to_vec3(self.ke)))
+        } else if transparent {
+            Rc::new(Dielectric::new(T::from(self.ni).unwrap()))
+        } else if max(self.ks) > max(self.kd) {
+            // map the phong exponent onto something that looks about as rough
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt().min(1.0);
+            Rc::new(Metal::new(to_vec3(self.ks), T::from(fuzz).unwrap()))
+        } else {
+            Rc::new(Lambertian::new(to_vec3(self.kd)))
+        }
+    }
+}
+
+/// Parses the contents of a `.mtl` file into materials by name.
+///
+/// Materials with a non-zero `Ke` become `DiffuseLight`, transparent materials (`d` < 1 or a
+/// refracting `illum`) become `Dielectric` with `Ni` as the refractive index, materials where `Ks`
+/// dominates `Kd` become `Metal` and everything else is `Lambertian`.
+pub fn parse_mtl<T, R>(src: &str) -> Result<HashMap<String, MaterialRef<T, R>>, Error>
+where
+    T: cgmath::BaseFloat + Send + Sync + 'static,
+    Standard: Distribution<T>,
+    R: rand::Rng + 'static,
+{
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlDescription)> = None;
+    for (line, keyword, args) in statements(src) {
+        if keyword == "newmtl" {
+            if let Some((name, desc)) = current.take() {
+                materials.insert(name, desc.build());
+            }
+            if args.is_empty() {
+                return Err(parse_error(line, "missing material name"));
+            }
+            current = Some((args.join(" "), MtlDescription::default()));
+            continue;
+        }
+
+        let desc = match &mut current {
+            Some((_, desc)) => desc,
+            None => return Err(parse_error(line, format!("`{}` before `newmtl`", keyword))),
+        };
+        match keyword {
+            "Kd" => desc.kd = color(line, &args)?,
+            "Ks" => desc.ks = color(line, &args)?,
+            "Ke" => desc.ke = color(line, &args)?,
+            "Ns" => desc.ns = floats(line, &args, 1, 1)?[0],
+            "Ni" => desc.ni = floats(line, &args, 1, 1)?[0],
+            "d" => desc.d = floats(line, &args, 1, 1)?[0],
+            "Tr" => desc.d = 1.0 - floats(line, &args, 1, 1)?[0],
+            "illum" => {
+                let illum = args
+                    .first()
+                    .and_then(|arg| arg.parse().ok())
+                    .ok_or_else(|| parse_error(line, "invalid illumination model"))?;
+                desc.illum = Some(illum);
+            }
+            // textures and everything else we can't represent
+            _ => {}
+        }
+    }
+    if let Some((name, desc)) = current.take() {
+        materials.insert(name, desc.build());
+    }
+    Ok(materials)
+}
+
+// all the faces using one material, with vertices deduplicated by their (v, vt, vn) indices
+struct Group<T, R> {
+    material: MaterialRef<T, R>,
+    lookup: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    positions: Vec<Vector3<T>>,
+    uvs: Vec<Option<Vector2<T>>>,
+    normals: Vec<Option<Vector3<T>>>,
+    indices: Vec<[usize; 3]>,
+}
+
+fn resolve_index(line: usize, index: &str, len: usize, kind: &str) -> Result<usize, Error> {
+    let i = index
+        .parse::<isize>()
+        .map_err(|_| parse_error(line, format!("invalid {} index `{}`", kind, index)))?;
+    // indices are 1-based and negative ones count back from the most recent element
+    let resolved = if i > 0 {
+        i as usize - 1
+    } else if i < 0 && i.unsigned_abs() <= len {
+        len - i.unsigned_abs()
+    } else {
+        len
+    };
+    if resolved < len {
+        Ok(resolved)
+    } else {
+        Err(parse_error(
+            line,
+            format!("{} index {} out of range", kind, index),
+        ))
+    }
+}
+
+/// Parses the contents of an `.obj` file. `mtllib` is called with the name of each referenced
+/// material library and should return its contents.
+pub fn parse_obj<T, R, F>(src: &str, mut mtllib: F) -> Result<Vec<TriangleMesh<T, R>>, Error>
+where
+    T: cgmath::BaseFloat + Send + Sync + 'static,
+    Standard: Distribution<T>,
+    R: rand::Rng + 'static,
+    F: FnMut(&str) -> std::io::Result<String>,
+{
+    let mut positions: Vec<Vector3<T>> = vec![];
+    let mut uvs: Vec<Vector2<T>> = vec![];
+    let mut normals: Vec<Vector3<T>> = vec![];
+    let mut materials: HashMap<String, MaterialRef<T, R>> = HashMap::new();
+    let mut groups: Vec<Group<T, R>> = vec![];
+    let mut group_lookup: HashMap<String, usize> = HashMap::new();
+    let mut current_material = String::new();
+
+    for (line, keyword, args) in statements(src) {
+        match keyword {
+            "v" => {
+                let v = floats(line, &args, 3, 4)?;
+                positions.push(to_vec3([v[0], v[1], v[2]]));
+            }
+            "vt" => {
+                let v = floats(line, &args, 1, 3)?;
+                let u = T::from(v[0]).unwrap();
+                let v = T::from(v.get(1).copied().unwrap_or(0.0)).unwrap();
+                uvs.push(vec2(u, v));
+            }
+            "vn" => {
+                let v = floats(line, &args, 3, 3)?;
+                normals.push(to_vec3([v[0], v[1], v[2]]));
+            }
+            "mtllib" => {
+                for file in args {
+                    let parsed = mtllib(file)
+                        .map_err(Error::Io)
+                        .and_then(|src| parse_mtl(&src));
+                    match parsed {
+                        Ok(parsed) => materials.extend(parsed),
+                        Err(error) => {
+                            return Err(Error::Mtl {
+                                file: file.to_owned(),
+                                error: Box::new(error),
+                            })
+                        }
+                    }
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                if !materials.contains_key(&name) {
+                    return Err(parse_error(line, format!("unknown material `{}`", name)));
+                }
+                current_material = name;
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(line, "a face needs at least 3 vertices"));
+                }
+
+                let group = match group_lookup.get(&current_material) {
+                    Some(index) => &mut groups[*index],
+                    None => {
+                        let material = match materials.get(&current_material) {
+                            Some(material) => Rc::clone(material),
+                            None => Rc::new(Lambertian::new(to_vec3([0.5, 0.5, 0.5]))),
+                        };
+                        group_lookup.insert(current_material.clone(), groups.len());
+                        groups.push(Group {
+                            material,
+                            lookup: HashMap::new(),
+                            positions: vec![],
+                            uvs: vec![],
+                            normals: vec![],
+                            indices: vec![],
+                        });
+                        groups.last_mut().unwrap()
+                    }
+                };
+
+                let mut face = Vec::with_capacity(args.len());
+                for vertex in args {
+                    let mut parts = vertex.split('/');
+                    let v = resolve_index(line, parts.next().unwrap(), positions.len(), "vertex")?;
+                    let vt = match parts.next() {
+                        Some(index) if !index.is_empty() => {
+                            Some(resolve_index(line, index, uvs.len(), "texture")?)
+                        }
+                        _ => None,
+                    };
+                    let vn = match parts.next() {
+                        Some(index) if !index.is_empty() => {
+                            Some(resolve_index(line, index, normals.len(), "normal")?)
+                        }
+                        _ => None,
+                    };
+
+                    let next = group.positions.len();
+                    let index = *group.lookup.entry((v, vt, vn)).or_insert(next);
+                    if index == next {
+                        group.positions.push(positions[v]);
+                        group.uvs.push(vt.map(|i| uvs[i]));
+                        group.normals.push(vn.map(|i| normals[i]));
+                    }
+                    face.push(index);
+                }
+
+                for i in 1..face.len() - 1 {
+                    group.indices.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            // groups, objects, smoothing groups and anything else we don't need
+            _ => {}
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|group| {
+            // a mesh only gets normals or uvs if every one of its vertices has them
+            let normals = group.normals.into_iter().collect::<Option<Vec<_>>>();
+            let uvs = group.uvs.into_iter().collect::<Option<Vec<_>>>();
+            TriangleMesh::new(group.positions, normals, uvs, group.indices, group.material)
+        })
+        .collect())
+}
+
+/// Loads an `.obj` file from disk, resolving material libraries relative to it.
+pub fn load<T, R, P>(path: P) -> Result<Vec<TriangleMesh<T, R>>, Error>
+where
+    T: cgmath::BaseFloat + Send + Sync + 'static,
+    Standard: Distribution<T>,
+    R: rand::Rng + 'static,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let src = std::fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_obj(&src, |file| std::fs::read_to_string(dir.join(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HitTable, Ray};
+    use rand::rngs::SmallRng;
+
+    fn parse(src: &str, mtl: &str) -> Result<Vec<TriangleMesh<f64, SmallRng>>, Error> {
+        let mtl = mtl.to_owned();
+        parse_obj(src, move |_| Ok(mtl.clone()))
+    }
+
+    #[test]
+    fn indices_resolve_from_either_end() {
+        assert_eq!(resolve_index(1, "1", 3, "vertex").unwrap(), 0);
+        assert_eq!(resolve_index(1, "3", 3, "vertex").unwrap(), 2);
+        assert_eq!(resolve_index(1, "-1", 3, "vertex").unwrap(), 2);
+        assert_eq!(resolve_index(1, "-3", 3, "vertex").unwrap(), 0);
+        for index in &["0", "4", "-4", "x", "-9223372036854775808"] {
+            assert!(resolve_index(1, index, 3, "vertex").is_err(), "{}", index);
+        }
+    }
+
+    #[test]
+    fn relative_indices_match_absolute_ones() {
+        let absolute = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "").unwrap();
+        let relative = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n", "").unwrap();
+        assert_eq!(absolute[0].positions(), relative[0].positions());
+        assert_eq!(absolute[0].indices(), relative[0].indices());
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv -1 1 0\nf 1 2 3 4 5\n";
+        let meshes = parse(src, "").unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].indices(), &[[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        match parse("v 0 0 0\nv 1 0 0\nf 1 2 9\n", "") {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn ke_makes_an_emitter() {
+        let src = "mtllib lamp.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl lamp\nf 1 2 3\n";
+        let meshes = parse(src, "newmtl lamp\nKd 0 0 0\nKe 4 3 2\n").unwrap();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let ray = Ray::new(vec3(0.25, 0.25, 1.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = meshes[0].hit(&mut rng, &ray, 0.0..f64::MAX).unwrap();
+        let emitted = hit.get_material().emitted(0.0, 0.0, hit.get_p());
+        assert_eq!(emitted, vec3(4.0, 3.0, 2.0));
+        assert!(meshes[0].material().is_emissive());
+    }
+}
@@ -0,0 +1,41 @@
+use cgmath::{vec3, InnerSpace, Vector3};
+
+/// An orthonormal basis with `w` along a given direction, for turning directions sampled around
+/// +z into world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Onb<T> {
+    u: Vector3<T>,
+    v: Vector3<T>,
+    w: Vector3<T>,
+}
+
+impl<T: cgmath::BaseFloat> Onb<T> {
+    pub fn from_w(n: &Vector3<T>) -> Self {
+        let w = n.normalize();
+        // any vector that isn't parallel to w will do
+        let a = if w.x.abs() > T::from(0.9).unwrap() {
+            vec3(T::zero(), T::one(), T::zero())
+        } else {
+            vec3(T::one(), T::zero(), T::zero())
+        };
+        let v = w.cross(a).normalize();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    pub fn u(&self) -> &Vector3<T> {
+        &self.u
+    }
+
+    pub fn v(&self) -> &Vector3<T> {
+        &self.v
+    }
+
+    pub fn w(&self) -> &Vector3<T> {
+        &self.w
+    }
+
+    pub fn local(&self, a: &Vector3<T>) -> Vector3<T> {
+        self.u * a.x + self.v * a.y + self.w * a.z
+    }
+}
@@ -0,0 +1,243 @@
+//! Image encoders for finished renders.
+//!
+//! Every writer takes rows top to bottom. The 8-bit formats take display ready `Pixel`s and the
+//! floating-point format takes linear radiance, before any tonemapping or gamma.
+
+use super::Pixel;
+use cgmath::Vector3;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// Binary (P6) portable pixmap.
+    Ppm,
+    /// Plain text (P3) portable pixmap.
+    PpmAscii,
+    Png,
+    /// Radiance RGBE.
+    Hdr,
+}
+
+impl Format {
+    /// Guesses the format from a file extension. Plain text PPM has to be asked for explicitly.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "hdr" => Some(Format::Hdr),
+            _ => None,
+        }
+    }
+}
+
+fn check_len(width: usize, height: usize, len: usize) -> std::io::Result<()> {
+    if width == 0 || height == 0 {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("can't write an empty {}x{} image", width, height),
+        ))
+    } else if width * height == len {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("expected {}x{} pixels, got {}", width, height, len),
+        ))
+    }
+}
+
+pub fn write_ppm<W: Write>(
+    mut w: W,
+    width: usize,
+    height: usize,
+    pixels: &[Pixel],
+) -> std::io::Result<()> {
+    check_len(width, height, pixels.len())?;
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    for p in pixels {
+        w.write_all(&[p.r, p.g, p.b])?;
+    }
+    w.flush()
+}
+
+pub fn write_ppm_ascii<W: Write>(
+    mut w: W,
+    width: usize,
+    height: usize,
+    pixels: &[Pixel],
+) -> std::io::Result<()> {
+    check_len(width, height, pixels.len())?;
+    write!(w, "P3\n{} {}\n255\n", width, height)?;
+    for row in pixels.chunks(width) {
+        for (i, p) in row.iter().enumerate() {
+            let separator = if i + 1 == row.len() { "\n" } else { " " };
+            write!(w, "{} {} {}{}", p.r, p.g, p.b, separator)?;
+        }
+    }
+    w.flush()
+}
+
+pub fn write_png<W: Write>(
+    w: W,
+    width: usize,
+    height: usize,
+    pixels: &[Pixel],
+) -> std::io::Result<()> {
+    check_len(width, height, pixels.len())?;
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let mut data = Vec::with_capacity(pixels.len() * 3);
+    for p in pixels {
+        data.extend_from_slice(&[p.r, p.g, p.b]);
+    }
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+// shared exponent encoding, as described in Graphics Gems II
+fn rgbe(c: &Vector3<f64>) -> [u8; 4] {
+    let v = c.x.max(c.y).max(c.z);
+    if v.is_nan() || v <= 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = mantissa * 2^exponent with the mantissa in [0.5, 1)
+    // saturating, since infinity's exponent casts to i32::MAX
+    let mut exponent = (v.log2().floor() as i32).saturating_add(1);
+    let mut mantissa = v / 2f64.powi(exponent);
+    if mantissa >= 1.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    }
+    if exponent > 127 {
+        return [255, 255, 255, 255];
+    }
+    let scale = mantissa * 256.0 / v;
+    [
+        (c.x.max(0.0) * scale) as u8,
+        (c.y.max(0.0) * scale) as u8,
+        (c.z.max(0.0) * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Writes run length encoded scanlines, which only exist for widths in `8..0x8000`. Anything else
+/// is written flat.
+pub fn write_hdr<W: Write>(
+    mut w: W,
+    width: usize,
+    height: usize,
+    pixels: &[Vector3<f64>],
+) -> std::io::Result<()> {
+    check_len(width, height, pixels.len())?;
+    write!(
+        w,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let encoded = pixels.iter().map(rgbe).collect::<Vec<_>>();
+    if !(8..0x8000).contains(&width) {
+        for p in encoded {
+            w.write_all(&p)?;
+        }
+        return w.flush();
+    }
+
+    for row in encoded.chunks(width) {
+        w.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+        // each channel is stored separately. only literal runs are used, which is valid if not
+        // the smallest encoding
+        for channel in 0..4 {
+            let bytes = row.iter().map(|p| p[channel]).collect::<Vec<_>>();
+            for chunk in bytes.chunks(128) {
+                w.write_all(&[chunk.len() as u8])?;
+                w.write_all(chunk)?;
+            }
+        }
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::vec3;
+
+    fn pixels(len: usize) -> Vec<Pixel> {
+        (0..len)
+            .map(|i| Pixel {
+                r: i as u8,
+                g: 1,
+                b: 2,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ppm_headers() {
+        let mut binary = vec![];
+        write_ppm(&mut binary, 2, 1, &pixels(2)).unwrap();
+        assert_eq!(binary, b"P6\n2 1\n255\n\x00\x01\x02\x01\x01\x02");
+
+        let mut ascii = vec![];
+        write_ppm_ascii(&mut ascii, 2, 1, &pixels(2)).unwrap();
+        assert_eq!(ascii, b"P3\n2 1\n255\n0 1 2 1 1 2\n");
+    }
+
+    #[test]
+    fn empty_or_mismatched_images_are_rejected() {
+        for &(width, height, len) in &[(0, 1, 0), (1, 0, 0), (2, 2, 3)] {
+            let error = write_ppm_ascii(vec![], width, height, &pixels(len)).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn png_dimensions() {
+        let mut data = vec![];
+        write_png(&mut data, 3, 2, &pixels(6)).unwrap();
+        let (info, _) = png::Decoder::new(&data[..]).read_info().unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+    }
+
+    #[test]
+    fn rgbe_encoding() {
+        assert_eq!(rgbe(&vec3(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(rgbe(&vec3(1.0, 1.0, 1.0)), [128, 128, 128, 129]);
+        assert_eq!(rgbe(&vec3(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(rgbe(&vec3(1e300, 0.0, 0.0)), [255, 255, 255, 255]);
+        assert_eq!(rgbe(&vec3(f64::INFINITY, 0.0, 0.0)), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn hdr_scanlines() {
+        const HEADER: &[u8] = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n";
+        let mut data = vec![];
+        write_hdr(&mut data, 8, 1, &[vec3(1.0, 1.0, 1.0); 8]).unwrap();
+        assert!(data.starts_with(HEADER));
+        let scanline = &data[HEADER.len()..];
+        assert_eq!(&scanline[..4], &[2, 2, 0, 8]);
+        // one literal run of 8 bytes for each channel
+        assert_eq!(scanline.len(), 4 + 4 * (1 + 8));
+        assert_eq!(
+            &scanline[4..13],
+            &[8, 128, 128, 128, 128, 128, 128, 128, 128]
+        );
+        assert_eq!(
+            &scanline[31..],
+            &[8, 129, 129, 129, 129, 129, 129, 129, 129]
+        );
+
+        // too narrow to run length encode
+        let mut data = vec![];
+        write_hdr(&mut data, 2, 1, &[vec3(1.0, 1.0, 1.0); 2]).unwrap();
+        assert_eq!(
+            &data[data.len() - 8..],
+            &[128, 128, 128, 129, 128, 128, 128, 129]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Probability densities over directions, for importance sampling.
+//!
+//! Densities are per unit solid angle.
+
+use super::{HitTable, Onb};
+use cgmath::{vec3, InnerSpace, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+
+pub trait Pdf<T, R> {
+    /// The density of `generate` picking `direction`, which needn't be normalized.
+    fn value(&self, rng: &mut R, direction: &Vector3<T>) -> T;
+
+    fn generate(&self, rng: &mut R) -> Vector3<T>;
+}
+
+/// A cosine distributed unit vector around +z.
+pub fn random_cosine_direction<T, R>(rng: &mut R) -> Vector3<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    let two = T::from(2.0).unwrap();
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let r1 = rng.gen::<T>();
+    let r2 = rng.gen::<T>();
+    let phi = two * pi * r1;
+    let r = r2.sqrt();
+    vec3(phi.cos() * r, phi.sin() * r, (T::one() - r2).sqrt())
+}
+
+/// Proportional to the cosine with a normal, like a perfectly diffuse surface reflects.
+pub struct CosinePdf<T> {
+    uvw: Onb<T>,
+}
+
+impl<T: cgmath::BaseFloat> CosinePdf<T> {
+    pub fn new(normal: &Vector3<T>) -> Self {
+        Self {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl<T, R> Pdf<T, R> for CosinePdf<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn value(&self, _rng: &mut R, direction: &Vector3<T>) -> T {
+        let cosine = direction.normalize().dot(*self.uvw.w());
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        (cosine / pi).max(T::zero())
+    }
+
+    fn generate(&self, rng: &mut R) -> Vector3<T> {
+        self.uvw.local(&random_cosine_direction(rng))
+    }
+}
+
+/// Toward an object as seen from `origin`. Used to aim rays at lights.
+pub struct HitTablePdf<'a, T, R> {
+    object: &'a dyn HitTable<T, R>,
+    origin: Vector3<T>,
+}
+
+impl<'a, T, R> HitTablePdf<'a, T, R> {
+    pub fn new(object: &'a dyn HitTable<T, R>, origin: Vector3<T>) -> Self {
+        Self { object, origin }
+    }
+}
+
+impl<'a, T: cgmath::BaseFloat, R> Pdf<T, R> for HitTablePdf<'a, T, R> {
+    fn value(&self, rng: &mut R, direction: &Vector3<T>) -> T {
+        self.object.pdf_value(rng, &self.origin, direction)
+    }
+
+    fn generate(&self, rng: &mut R) -> Vector3<T> {
+        self.object.random(rng, &self.origin)
+    }
+}
+
+/// Picks from `a` with probability `weight`, otherwise from `b`.
+pub struct MixturePdf<'a, T, R> {
+    a: &'a dyn Pdf<T, R>,
+    b: &'a dyn Pdf<T, R>,
+    weight: T,
+}
+
+impl<'a, T, R> MixturePdf<'a, T, R> {
+    pub fn new(a: &'a dyn Pdf<T, R>, b: &'a dyn Pdf<T, R>, weight: T) -> Self {
+        Self { a, b, weight }
+    }
+}
+
+impl<'a, T, R> Pdf<T, R> for MixturePdf<'a, T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn value(&self, rng: &mut R, direction: &Vector3<T>) -> T {
+        self.weight * self.a.value(rng, direction)
+            + (T::one() - self.weight) * self.b.value(rng, direction)
+    }
+
+    fn generate(&self, rng: &mut R) -> Vector3<T> {
+        if rng.gen::<T>() < self.weight {
+            self.a.generate(rng)
+        } else {
+            self.b.generate(rng)
+        }
+    }
+}
@@ -5,3 +5,15 @@ pub struct Pixel {
     pub g: u8,
     pub b: u8,
 }
+
+impl Pixel {
+    /// Gamma corrects and quantizes a linear color.
+    pub fn from_linear(c: cgmath::Vector3<f64>, gamma: f64) -> Self {
+        let encode = |v: f64| (v.powf(1.0 / gamma) * 255.99) as u8;
+        Self {
+            r: encode(c.x),
+            g: encode(c.y),
+            b: encode(c.z),
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! A metallic-roughness material, the model glTF and most asset pipelines author for.
+//!
+//! Specular reflection is a GGX (Trowbridge-Reitz) microfacet lobe with height-correlated Smith
+//! masking-shadowing and Schlick's Fresnel. Whatever the non-metallic part doesn't reflect
+//! specularly is scattered diffusely.
+
+use super::pdf::random_cosine_direction;
+use super::{HitRecord, Material, Onb, Ray, SolidColor, Texture};
+use cgmath::{vec3, InnerSpace, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+use std::sync::Arc as Rc;
+
+// the reflectance of dielectrics head on, which covers most of them well enough
+const DIELECTRIC_F0: f64 = 0.04;
+
+// a perfectly smooth surface has a delta distribution, which can't be sampled or evaluated
+const MIN_ALPHA: f64 = 1e-3;
+
+// `a` in the frame of `uvw`
+fn to_local<T: cgmath::BaseFloat>(uvw: &Onb<T>, a: &Vector3<T>) -> Vector3<T> {
+    vec3(a.dot(*uvw.u()), a.dot(*uvw.v()), a.dot(*uvw.w()))
+}
+
+// the density of microfacet normals at cosine `cos_h` with the macro normal
+fn ggx_distribution<T: cgmath::BaseFloat>(cos_h: T, alpha: T) -> T {
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let a2 = alpha * alpha;
+    let d = cos_h * cos_h * (a2 - T::one()) + T::one();
+    a2 / (pi * d * d)
+}
+
+// Smith's lambda for GGX, from which both the masking and shadowing terms follow
+fn smith_lambda<T: cgmath::BaseFloat>(cos: T, alpha: T) -> T {
+    let cos2 = cos * cos;
+    let tan2 = (T::one() - cos2).max(T::zero()) / cos2;
+    ((T::one() + alpha * alpha * tan2).sqrt() - T::one()) / T::from(2.0).unwrap()
+}
+
+fn schlick_fresnel<T: cgmath::BaseFloat>(f0: Vector3<T>, cos: T) -> Vector3<T> {
+    let one = T::one();
+    let m = (one - cos).max(T::zero()).min(one);
+    let m5 = m * m * m * m * m;
+    f0 + (vec3(one, one, one) - f0) * m5
+}
+
+// Heitz's sampling of the microfacet normals visible from `v`, in a frame with the macro normal
+// along +z
+fn sample_visible_normal<T, R>(rng: &mut R, v: Vector3<T>, alpha: T) -> Vector3<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    let (zero, one, two) = (T::zero(), T::one(), T::from(2.0).unwrap());
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    // stretch the surface so that the microfacets form a hemisphere
+    let vh = vec3(alpha * v.x, alpha * v.y, v.z).normalize();
+    let len2 = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if len2 > zero {
+        vec3(-vh.y, vh.x, zero) / len2.sqrt()
+    } else {
+        vec3(one, zero, zero)
+    };
+    let t2 = vh.cross(t1);
+    // a uniform point on a disk, squashed onto the half of it the hemisphere shows to `v`
+    let r = rng.gen::<T>().sqrt();
+    let phi = two * pi * rng.gen::<T>();
+    let p1 = r * phi.cos();
+    let s = (one + vh.z) / two;
+    let p2 = (one - s) * (one - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = t1 * p1 + t2 * p2 + vh * (one - p1 * p1 - p2 * p2).max(zero).sqrt();
+    // and unstretched
+    vec3(alpha * nh.x, alpha * nh.y, nh.z.max(zero)).normalize()
+}
+
+/// A physically based surface described by a base color, how metallic it is and how rough.
+///
+/// Metals tint their reflections with the base color and have no diffuse part, dielectrics
+/// reflect a few percent specularly and scatter the rest with the base color. Both `metallic`
+/// and `roughness` run from 0 to 1, and roughness is squared to give GGX's alpha.
+pub struct Principled<T> {
+    base_color: Rc<dyn Texture<T> + Send + Sync>,
+    metallic: T,
+    roughness: T,
+    metallic_roughness: Option<Rc<dyn Texture<T> + Send + Sync>>,
+    emission: Option<Rc<dyn Texture<T> + Send + Sync>>,
+}
+
+impl<T: Copy + Send + Sync + 'static> Principled<T> {
+    pub fn new(base_color: Vector3<T>, metallic: T, roughness: T) -> Self {
+        Self::textured(Rc::new(SolidColor::new(base_color)), metallic, roughness)
+    }
+}
+
+impl<T> Principled<T> {
+    pub fn textured(
+        base_color: Rc<dyn Texture<T> + Send + Sync>,
+        metallic: T,
+        roughness: T,
+    ) -> Self {
+        Self {
+            base_color,
+            metallic,
+            roughness,
+            metallic_roughness: None,
+            emission: None,
+        }
+    }
+
+    /// Scales roughness by the texture's green channel and metallic by its blue, the way glTF
+    /// packs them.
+    pub fn with_metallic_roughness(mut self, texture: Rc<dyn Texture<T> + Send + Sync>) -> Self {
+        self.metallic_roughness = Some(texture);
+        self
+    }
+
+    /// Makes the surface glow as well as reflect.
+    pub fn with_emission(mut self, texture: Rc<dyn Texture<T> + Send + Sync>) -> Self {
+        self.emission = Some(texture);
+        self
+    }
+}
+
+// the parameters at a single point of the surface
+struct Surface<T> {
+    base_color: Vector3<T>,
+    metallic: T,
+    alpha: T,
+}
+
+impl<T: cgmath::BaseFloat> Surface<T> {
+    // reflectance at normal incidence
+    fn f0(&self) -> Vector3<T> {
+        let dielectric = T::from(DIELECTRIC_F0).unwrap();
+        vec3(dielectric, dielectric, dielectric) * (T::one() - self.metallic)
+            + self.base_color * self.metallic
+    }
+
+    // the diffuse albedo seen from `cos_v`: what the dielectric part doesn't reflect specularly.
+    // going by the fresnel term of each microfacet instead would let grazing light come back
+    // brighter than it arrived
+    fn diffuse(&self, cos_v: T) -> Vector3<T> {
+        let dielectric = T::from(DIELECTRIC_F0).unwrap();
+        let fresnel = schlick_fresnel(vec3(dielectric, dielectric, dielectric), cos_v);
+        self.base_color * ((T::one() - fresnel.x) * (T::one() - self.metallic))
+    }
+
+    // how often `scatter` samples the specular lobe rather than the diffuse one, roughly in
+    // proportion to how much each reflects toward `cos_v`
+    fn specular_probability(&self, cos_v: T) -> T {
+        let three = T::from(3.0).unwrap();
+        let one = T::one();
+        let fresnel = schlick_fresnel(self.f0(), cos_v);
+        let specular = (fresnel.x + fresnel.y + fresnel.z) / three;
+        let diffuse = self.diffuse(cos_v);
+        let diffuse = (diffuse.x + diffuse.y + diffuse.z) / three;
+        if specular + diffuse > T::zero() {
+            specular / (specular + diffuse)
+        } else {
+            one
+        }
+    }
+
+    // the reflectance from `v` toward `l` times the cosine with `l`, and the density `scatter`
+    // picks `l` with. both are unit vectors in the local frame, facing away from the surface
+    fn evaluate(&self, v: Vector3<T>, l: Vector3<T>) -> (Vector3<T>, T) {
+        let (zero, one) = (T::zero(), T::one());
+        if v.z <= zero || l.z <= zero {
+            return (vec3(zero, zero, zero), zero);
+        }
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        let four = T::from(4.0).unwrap();
+        let alpha = self.alpha;
+
+        let h = (v + l).normalize();
+        let fresnel = schlick_fresnel(self.f0(), v.dot(h));
+        let d = ggx_distribution(h.z, alpha);
+        let (lambda_v, lambda_l) = (smith_lambda(v.z, alpha), smith_lambda(l.z, alpha));
+        // the cosine with `l` cancels against the denominator of the microfacet model
+        let specular = fresnel * (d / ((one + lambda_v + lambda_l) * four * v.z));
+        let diffuse = self.diffuse(v.z) * (l.z / pi);
+
+        let p = self.specular_probability(v.z);
+        let specular_pdf = d / ((one + lambda_v) * four * v.z);
+        let diffuse_pdf = l.z / pi;
+        (
+            specular + diffuse,
+            p * specular_pdf + (one - p) * diffuse_pdf,
+        )
+    }
+}
+
+impl<T: cgmath::BaseFloat> Principled<T> {
+    fn surface(&self, u: T, v: T, p: &Vector3<T>) -> Surface<T> {
+        let (zero, one) = (T::zero(), T::one());
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(texture) = &self.metallic_roughness {
+            let texel = texture.value(u, v, p);
+            roughness *= texel.y;
+            metallic *= texel.z;
+        }
+        let roughness = roughness.max(zero).min(one);
+        Surface {
+            base_color: self.base_color.value(u, v, p),
+            metallic: metallic.max(zero).min(one),
+            alpha: (roughness * roughness).max(T::from(MIN_ALPHA).unwrap()),
+        }
+    }
+}
+
+impl<T, R> Material<T, R> for Principled<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn scatter(
+        &self,
+        rng: &mut R,
+        r: &Ray<T>,
+        rec: &HitRecord<T, R>,
+    ) -> Option<(Vector3<T>, Ray<T>)> {
+        let uvw = Onb::from_w(rec.get_normal());
+        let v = to_local(&uvw, &-r.direction().normalize());
+        // interpolated normals can face away from the ray even though the surface doesn't
+        if v.z <= T::zero() {
+            return None;
+        }
+        let uv = rec.get_uv();
+        let surface = self.surface(uv.x, uv.y, rec.get_p());
+
+        let l = if rng.gen::<T>() < surface.specular_probability(v.z) {
+            let h = sample_visible_normal(rng, v, surface.alpha);
+            h * (v.dot(h) * T::from(2.0).unwrap()) - v
+        } else {
+            random_cosine_direction(rng)
+        };
+        // reflections off steep microfacets can still point into the surface
+        let (f, pdf) = surface.evaluate(v, l);
+        if pdf <= T::zero() {
+            return None;
+        }
+        let scattered = Ray::new(*rec.get_p(), uvw.local(&l), r.time());
+        Some((f / pdf, scattered))
+    }
+
+    fn bsdf(
+        &self,
+        r: &Ray<T>,
+        rec: &HitRecord<T, R>,
+        direction: &Vector3<T>,
+    ) -> Option<(Vector3<T>, T)> {
+        let uvw = Onb::from_w(rec.get_normal());
+        let v = to_local(&uvw, &-r.direction().normalize());
+        let l = to_local(&uvw, &direction.normalize());
+        let uv = rec.get_uv();
+        Some(self.surface(uv.x, uv.y, rec.get_p()).evaluate(v, l))
+    }
+
+    fn emitted(&self, u: T, v: T, p: &Vector3<T>) -> Vector3<T> {
+        match &self.emission {
+            Some(texture) => texture.value(u, v, p),
+            None => vec3(T::zero(), T::zero(), T::zero()),
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emission.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::vec2;
+    use rand::rngs::SmallRng;
+
+    const ROUGHNESS: [f64; 3] = [0.0, 0.5, 1.0];
+    const METALLIC: [f64; 3] = [0.0, 0.5, 1.0];
+
+    // a hit on the xy plane, facing +z, from a ray coming in at `angle` degrees off the normal
+    fn hit(material: Principled<f64>, angle: f64) -> (Ray<f64>, HitRecord<f64, SmallRng>) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let r = Ray::new(vec3(-sin, 0.0, cos), vec3(sin, 0.0, -cos), 0.0);
+        let rec = HitRecord::new(
+            1.0,
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec2(0.5, 0.5),
+            Rc::new(material),
+        );
+        (r, rec)
+    }
+
+    // specular samples that reflect into the surface are dropped, so the density only integrates
+    // to 1 if none are
+    #[test]
+    fn pdf_integrates_to_the_chance_of_scattering() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for &roughness in &ROUGHNESS[1..] {
+            for &angle in &[0.0f64, 30.0, 60.0] {
+                let material = Principled::new(vec3(0.8, 0.8, 0.8), 0.5, roughness);
+                let surface = material.surface(0.5, 0.5, &vec3(0.0, 0.0, 0.0));
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let v = vec3(sin, 0.0, cos);
+                let (r, rec) = hit(material, angle);
+                // uniform over the hemisphere, a density of 1 / 2pi
+                let n = 200_000;
+                let integral = (0..n)
+                    .map(|_| {
+                        let z = rng.gen::<f64>();
+                        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+                        let r = (1.0 - z * z).sqrt();
+                        let l = vec3(r * phi.cos(), r * phi.sin(), z);
+                        surface.evaluate(v, l).1 * 2.0 * std::f64::consts::PI
+                    })
+                    .sum::<f64>()
+                    / n as f64;
+                let scattered = (0..n)
+                    .filter(|_| rec.get_material().scatter(&mut rng, &r, &rec).is_some())
+                    .count() as f64
+                    / n as f64;
+                assert!(
+                    (integral - scattered).abs() < 0.02,
+                    "{} {}",
+                    integral,
+                    scattered
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scatter_agrees_with_bsdf() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for &metallic in &METALLIC {
+            for &roughness in &ROUGHNESS {
+                let material = Principled::new(vec3(0.9, 0.6, 0.3), metallic, roughness);
+                let (r, rec) = hit(material, 40.0);
+                for _ in 0..1000 {
+                    let (weight, scattered) = match rec.get_material().scatter(&mut rng, &r, &rec) {
+                        Some(scattered) => scattered,
+                        None => continue,
+                    };
+                    let (f, pdf) = rec
+                        .get_material()
+                        .bsdf(&r, &rec, scattered.direction())
+                        .unwrap();
+                    assert!(pdf > 0.0);
+                    assert!((weight - f / pdf).magnitude() <= 1e-6 * weight.magnitude().max(1.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn white_furnace() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for &metallic in &METALLIC {
+            for &roughness in &ROUGHNESS {
+                for &angle in &[0.0, 45.0, 80.0] {
+                    let material = Principled::new(vec3(1.0, 1.0, 1.0), metallic, roughness);
+                    let (r, rec) = hit(material, angle);
+                    let n = 20_000;
+                    let albedo = (0..n)
+                        .filter_map(|_| rec.get_material().scatter(&mut rng, &r, &rec))
+                        .fold(vec3(0.0, 0.0, 0.0), |acc, (weight, _)| acc + weight)
+                        / n as f64;
+                    assert!(
+                        albedo.x <= 1.01,
+                        "{} {} {} {:?}",
+                        metallic,
+                        roughness,
+                        angle,
+                        albedo
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,29 @@
 extern crate cgmath;
 
+use super::spectrum::Wavelengths;
 use cgmath::Vector3;
 
 pub struct Ray<T> {
     a: Vector3<T>,
     b: Vector3<T>,
+    time: T,
+    wavelengths: Option<Wavelengths<T>>,
 }
 
 impl<T: cgmath::BaseNum> Ray<T> {
-    pub fn new(a: Vector3<T>, b: Vector3<T>) -> Self {
-        Ray { a, b }
+    /// `time` is when, while the shutter is open, the ray was cast.
+    pub fn new(a: Vector3<T>, b: Vector3<T>, time: T) -> Self {
+        Ray {
+            a,
+            b,
+            time,
+            wavelengths: None,
+        }
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Wavelengths<T>) -> Self {
+        self.wavelengths = Some(wavelengths);
+        self
     }
 
     pub fn origin(&self) -> &Vector3<T> {
@@ -20,6 +34,16 @@ impl<T: cgmath::BaseNum> Ray<T> {
         &self.b
     }
 
+    pub fn time(&self) -> T {
+        self.time
+    }
+
+    /// What the path the ray is on is followed at. `None` for rays that weren't cast by the
+    /// renderer, and rays scattered by materials that leave them to the path.
+    pub fn wavelengths(&self) -> Option<&Wavelengths<T>> {
+        self.wavelengths.as_ref()
+    }
+
     pub fn point_at_parameter(&self, t: T) -> Vector3<T> {
         self.a + self.b * t
     }
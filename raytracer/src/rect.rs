@@ -0,0 +1,310 @@
+use super::{Aabb, HitRecord, HitTable, Material, Ray};
+use cgmath::{vec2, InnerSpace, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+use std::ops::Range;
+use std::sync::Arc as Rc;
+
+// rects are flat, so their boxes get a little thickness to keep the slab test happy
+const PAD: f64 = 1e-4;
+
+/// A rectangle spanning axes `a` and `b` at `k` along the third axis.
+struct AxisRect<T, R> {
+    axes: [usize; 3],
+    a: Range<T>,
+    b: Range<T>,
+    k: T,
+    flipped: bool,
+    material: Rc<dyn Material<T, R> + Send + Sync>,
+}
+
+impl<T: cgmath::BaseFloat, R> AxisRect<T, R> {
+    fn normal(&self) -> Vector3<T> {
+        let mut normal = Vector3::new(T::zero(), T::zero(), T::zero());
+        normal[self.axes[2]] = if self.flipped { -T::one() } else { T::one() };
+        normal
+    }
+
+    fn hit(&self, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let [a, b, k] = self.axes;
+        let t_hit = (self.k - r.origin()[k]) / r.direction()[k];
+        // also rejects NaN, from rays parallel to the rect
+        if !(t_hit > t.start && t_hit < t.end) {
+            return None;
+        }
+        let p = r.point_at_parameter(t_hit);
+        if p[a] < self.a.start || p[a] > self.a.end || p[b] < self.b.start || p[b] > self.b.end {
+            return None;
+        }
+        let uv = vec2(
+            (p[a] - self.a.start) / (self.a.end - self.a.start),
+            (p[b] - self.b.start) / (self.b.end - self.b.start),
+        );
+        let mut rec = HitRecord::new(t_hit, p, self.normal(), uv, Rc::clone(&self.material));
+        rec.set_face_normal(r, self.normal());
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb<T> {
+        let [a, b, k] = self.axes;
+        let pad = T::from(PAD).unwrap();
+        let mut min = Vector3::new(T::zero(), T::zero(), T::zero());
+        let mut max = min;
+        min[a] = self.a.start;
+        max[a] = self.a.end;
+        min[b] = self.b.start;
+        max[b] = self.b.end;
+        min[k] = self.k - pad;
+        max[k] = self.k + pad;
+        Aabb::new(min, max)
+    }
+
+    // uniform over the rect's area, converted to solid angle
+    fn pdf_value(&self, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        let ray = Ray::new(*origin, *direction, T::zero());
+        match self.hit(&ray, T::zero()..T::infinity()) {
+            None => T::zero(),
+            Some(rec) => {
+                let area = (self.a.end - self.a.start) * (self.b.end - self.b.start);
+                let length2 = direction.magnitude2();
+                let distance2 = rec.get_t() * rec.get_t() * length2;
+                let cosine = direction.dot(*rec.get_normal()).abs() / length2.sqrt();
+                distance2 / (cosine * area)
+            }
+        }
+    }
+
+    fn random<G>(&self, rng: &mut G, origin: &Vector3<T>) -> Vector3<T>
+    where
+        Standard: Distribution<T>,
+        G: rand::Rng,
+    {
+        let [a, b, k] = self.axes;
+        let mut p = Vector3::new(T::zero(), T::zero(), T::zero());
+        p[a] = self.a.start + rng.gen::<T>() * (self.a.end - self.a.start);
+        p[b] = self.b.start + rng.gen::<T>() * (self.b.end - self.b.start);
+        p[k] = self.k;
+        p - origin
+    }
+}
+
+/// A rectangle in the plane `z = k`, facing +z with `u` along x and `v` along y.
+pub struct XYRect<T, R>(AxisRect<T, R>);
+
+impl<T, R> XYRect<T, R> {
+    pub fn new(
+        x: Range<T>,
+        y: Range<T>,
+        k: T,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        Self(AxisRect {
+            axes: [0, 1, 2],
+            a: x,
+            b: y,
+            k,
+            flipped: false,
+            material,
+        })
+    }
+
+    /// Faces -z instead.
+    pub fn flipped(mut self) -> Self {
+        self.0.flipped = !self.0.flipped;
+        self
+    }
+}
+
+impl<T, R> HitTable<T, R> for XYRect<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        self.0.hit(r, t)
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(self.0.bounding_box())
+    }
+
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        self.0.pdf_value(origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.0.random(rng, origin)
+    }
+}
+
+/// A rectangle in the plane `y = k`, facing +y with `u` along x and `v` along z.
+pub struct XZRect<T, R>(AxisRect<T, R>);
+
+impl<T, R> XZRect<T, R> {
+    pub fn new(
+        x: Range<T>,
+        z: Range<T>,
+        k: T,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        Self(AxisRect {
+            axes: [0, 2, 1],
+            a: x,
+            b: z,
+            k,
+            flipped: false,
+            material,
+        })
+    }
+
+    /// Faces -y instead.
+    pub fn flipped(mut self) -> Self {
+        self.0.flipped = !self.0.flipped;
+        self
+    }
+}
+
+impl<T, R> HitTable<T, R> for XZRect<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        self.0.hit(r, t)
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(self.0.bounding_box())
+    }
+
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        self.0.pdf_value(origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.0.random(rng, origin)
+    }
+}
+
+/// A rectangle in the plane `x = k`, facing +x with `u` along y and `v` along z.
+pub struct YZRect<T, R>(AxisRect<T, R>);
+
+impl<T, R> YZRect<T, R> {
+    pub fn new(
+        y: Range<T>,
+        z: Range<T>,
+        k: T,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        Self(AxisRect {
+            axes: [1, 2, 0],
+            a: y,
+            b: z,
+            k,
+            flipped: false,
+            material,
+        })
+    }
+
+    /// Faces -x instead.
+    pub fn flipped(mut self) -> Self {
+        self.0.flipped = !self.0.flipped;
+        self
+    }
+}
+
+impl<T, R> HitTable<T, R> for YZRect<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        self.0.hit(r, t)
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(self.0.bounding_box())
+    }
+
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        self.0.pdf_value(origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.0.random(rng, origin)
+    }
+}
+
+/// An axis-aligned box made of six rects, with normals facing out.
+pub struct Cuboid<T, R> {
+    min: Vector3<T>,
+    max: Vector3<T>,
+    sides: [AxisRect<T, R>; 6],
+}
+
+impl<T: Copy, R> Cuboid<T, R> {
+    pub fn new(
+        min: Vector3<T>,
+        max: Vector3<T>,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        let side = |axes: [usize; 3], flipped| {
+            let [a, b, k] = axes;
+            AxisRect {
+                axes,
+                a: min[a]..max[a],
+                b: min[b]..max[b],
+                k: if flipped { min[k] } else { max[k] },
+                flipped,
+                material: Rc::clone(&material),
+            }
+        };
+        let sides = [
+            side([0, 1, 2], false),
+            side([0, 1, 2], true),
+            side([0, 2, 1], false),
+            side([0, 2, 1], true),
+            side([1, 2, 0], false),
+            side([1, 2, 0], true),
+        ];
+        Self { min, max, sides }
+    }
+}
+
+impl<T, R> HitTable<T, R> for Cuboid<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let mut closest_so_far = t.end;
+        let mut hit = None;
+        for side in self.sides.iter() {
+            if let Some(rec) = side.hit(r, t.start..closest_so_far) {
+                closest_so_far = rec.get_t();
+                hit = Some(rec);
+            }
+        }
+        hit
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(Aabb::new(self.min, self.max))
+    }
+
+    // sampling picks a side uniformly, so the density is the mean of theirs
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        let sum = self.sides.iter().fold(T::zero(), |acc, side| {
+            acc + side.pdf_value(origin, direction)
+        });
+        sum / T::from(self.sides.len()).unwrap()
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.sides[rng.gen_range(0, self.sides.len())].random(rng, origin)
+    }
+}
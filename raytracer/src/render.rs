@@ -0,0 +1,50 @@
+use super::settings::InvalidSettings;
+use super::spectrum::{Radiance, Wavelengths};
+use super::{Integrator, Pixel, RenderSettings, Sampler, Scene};
+use cgmath::Vector3;
+
+/// Everything needed to shade a pixel of a scene. Shared between the viewer and headless renders.
+pub struct Renderer<R> {
+    scene: Scene<R>,
+    integrator: Box<dyn Integrator<R> + Send + Sync>,
+}
+
+impl<R: Sampler> Renderer<R> {
+    /// Renders with the scene's settings, so long as they're valid.
+    pub fn new(scene: Scene<R>) -> Result<Self, InvalidSettings> {
+        scene.render.validate()?;
+        let integrator = scene.render.integrator.build();
+        Ok(Self { scene, integrator })
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.scene.render
+    }
+
+    /// The linear radiance along the ray through a pixel for the sampler's current sample. `y`
+    /// counts up from the bottom of the image.
+    pub fn trace(&self, x: usize, y: usize, sampler: &mut R) -> Radiance {
+        let settings = self.settings();
+        let u = (x as f64 + sampler.next_dimension()) / (settings.width as f64);
+        let v = (y as f64 + sampler.next_dimension()) / (settings.height as f64);
+
+        let r = self.scene.camera.ray(sampler, u, v);
+        let r = r.with_wavelengths(Wavelengths::sample(sampler.next_dimension()));
+        self.integrator.radiance(&self.scene, sampler, &r)
+    }
+
+    /// The mean of `samples_per_pixel` traces through a pixel, one for each of the sampler's
+    /// first samples.
+    pub fn sample(&self, x: usize, y: usize, sampler: &mut R) -> Vector3<f64> {
+        let mut sum = Radiance::default();
+        for i in 0..self.settings().samples_per_pixel {
+            sampler.start_sample(i);
+            sum += self.trace(x, y, sampler);
+        }
+        sum.to_rgb() / self.settings().samples_per_pixel as f64
+    }
+
+    pub fn draw(&self, x: usize, y: usize, sampler: &mut R) -> Pixel {
+        Pixel::from_linear(self.sample(x, y, sampler), self.settings().gamma)
+    }
+}
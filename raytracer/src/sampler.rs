@@ -0,0 +1,695 @@
+//! Where the random numbers behind each pixel come from.
+//!
+//! A sampler hands out the samples of one pixel, each a point in as many dimensions as its path
+//! needs: the first two place it in the pixel, the next two on the lens, then the time and
+//! whatever the lights and materials ask for along the way. Samplers are `rand::RngCore`s that
+//! take the next dimension on every draw, so anything that takes an rng can take a sampler.
+//!
+//! Samplers that spread a pixel's samples more evenly than independent random numbers do make
+//! images converge faster at the same sample count.
+
+use super::RenderSettings;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub trait Sampler: RngCore {
+    /// Moves on to the pixel's `index`th sample, back at its first dimension.
+    fn start_sample(&mut self, index: usize);
+
+    /// The next dimension of the current sample, in `0..1`.
+    fn next_dimension(&mut self) -> f64;
+}
+
+impl<S: Sampler + ?Sized> Sampler for Box<S> {
+    fn start_sample(&mut self, index: usize) {
+        (**self).start_sample(index)
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        (**self).next_dimension()
+    }
+}
+
+// every draw takes a dimension, whatever type it's for
+macro_rules! impl_rng_core {
+    ($($sampler:ty),*) => {$(
+        impl RngCore for $sampler {
+            fn next_u32(&mut self) -> u32 {
+                (self.next_dimension() * 4_294_967_296.0) as u32
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                (self.next_dimension() * 18_446_744_073_709_551_616.0) as u64
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                for chunk in dest.chunks_mut(8) {
+                    let bytes = self.next_u64().to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_rng_core!(Stratified, Halton, Sobol, BlueNoise);
+
+/// Picks a sampler, from a scene file or the command line.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl std::str::FromStr for SamplerKind {
+    type Err = ron::Error;
+
+    /// Parses the same syntax scene files use, like `Sobol`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ron::from_str(s)
+    }
+}
+
+impl SamplerKind {
+    /// The sampler for pixel (`x`, `y`) of an image rendered with `settings`.
+    pub fn build(&self, settings: &RenderSettings, x: usize, y: usize) -> Box<dyn Sampler> {
+        let seed = settings.pixel_seed(x, y, 0);
+        match *self {
+            SamplerKind::Independent => Box::new(Independent::new(seed)),
+            SamplerKind::Stratified => Box::new(Stratified::new(seed, settings.samples_per_pixel)),
+            SamplerKind::Halton => Box::new(Halton::new(seed)),
+            SamplerKind::Sobol => Box::new(Sobol::new(seed)),
+            SamplerKind::BlueNoise => Box::new(BlueNoise::new(settings.seed, x, y)),
+        }
+    }
+}
+
+// splitmix64's finalizer, which spreads every bit of `x` over the result
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn hash(a: u64, b: u64) -> u64 {
+    mix(a ^ mix(b.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+}
+
+// uniform in 0..1
+fn to_unit(x: u64) -> f64 {
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Plain random numbers, with every dimension independent of the others.
+///
+/// Every sample reseeds a `SmallRng` from the pixel's seed and the sample's index, so a sample
+/// draws the same numbers whether it's taken on its own, as the viewer takes them pass by pass,
+/// or after the pixel's other samples, as the headless renderer takes them.
+pub struct Independent {
+    seed: u64,
+    rng: SmallRng,
+}
+
+impl Independent {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for Independent {
+    fn start_sample(&mut self, index: usize) {
+        self.rng = SmallRng::seed_from_u64(self.seed ^ ((index as u64) << 32));
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        self.rng.gen()
+    }
+}
+
+impl RngCore for Independent {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Kensler's hashed permutation of `0..len`, from Correlated Multi-Jittered Sampling
+fn permute(mut i: u32, len: u32, p: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        // cycle walk until the permutation of the next power of two lands in range
+        if i < len {
+            return i.wrapping_add(p) % len;
+        }
+    }
+}
+
+// and its hashed jitter, in 0..1
+fn jitter(mut i: u32, p: u32) -> f64 {
+    i ^= p;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb365_34e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc_4795);
+    i ^= 0xdf6e_307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | p >> 18);
+    i as f64 / 4_294_967_808.0
+}
+
+// the most nearly square grid of exactly `count` cells, as columns by rows.
+// `u32::is_multiple_of` is too new for older toolchains, which don't know the lint either
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn grid(count: u32) -> (u32, u32) {
+    let columns = (1..=count)
+        .take_while(|c| c * c <= count)
+        .filter(|&c| count % c == 0)
+        .last()
+        .unwrap_or(1);
+    (columns, count / columns)
+}
+
+// sample `s` in pattern `p`, on a grid of columns by rows with one sample per cell, one per
+// column and one per row
+fn multi_jittered(s: u32, (columns, rows): (u32, u32), p: u32) -> [f64; 2] {
+    let s = permute(s, columns * rows, p.wrapping_mul(0x5163_3e2d));
+    let (column, row) = (s % columns, s / columns);
+    let sx = permute(column, columns, p.wrapping_mul(0xa511_e9b3));
+    let sy = permute(row, rows, p.wrapping_mul(0x63d8_3595));
+    let jx = jitter(s, p.wrapping_mul(0xa399_d265));
+    let jy = jitter(s, p.wrapping_mul(0x711a_d6a5));
+    [
+        (column as f64 + (sy as f64 + jx) / rows as f64) / columns as f64,
+        (row as f64 + (sx as f64 + jy) / columns as f64) / rows as f64,
+    ]
+}
+
+/// Jittered samples, stratified over each pair of dimensions in turn.
+///
+/// The pixel's samples split each pair into the most nearly square grid of exactly as many
+/// cells, with one sample in each cell, column and row, after Kensler's correlated
+/// multi-jittered sampling. A prime count makes a grid one column wide, which still stratifies
+/// each dimension on its own. Pairs are shuffled independently of each other. Samples past
+/// `count` start a fresh pattern.
+pub struct Stratified {
+    seed: u64,
+    grid: (u32, u32),
+    index: usize,
+    dimension: usize,
+}
+
+impl Stratified {
+    pub fn new(seed: u64, count: usize) -> Self {
+        Self {
+            seed,
+            grid: grid(count.max(1) as u32),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        let (pair, axis) = (self.dimension / 2, self.dimension % 2);
+        self.dimension += 1;
+        let count = (self.grid.0 * self.grid.1) as usize;
+        let round = (self.index / count) as u64;
+        let pattern = hash(hash(self.seed, round), pair as u64) as u32;
+        let s = (self.index % count) as u32;
+        multi_jittered(s, self.grid, pattern)[axis].min(1.0 - f64::EPSILON / 2.0)
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// `i`'s digits in `base`, mirrored about the radix point
+fn radical_inverse(base: u64, mut i: u64) -> f64 {
+    let inverse = 1.0 / base as f64;
+    let mut scale = inverse;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * scale;
+        i /= base;
+        scale *= inverse;
+    }
+    result
+}
+
+/// The Halton sequence, with a prime base per dimension and each dimension shifted randomly per
+/// pixel (a Cranley-Patterson rotation) so that neighbouring pixels don't repeat each other.
+///
+/// Past the 64th dimension, where the bases get too large to cover a pixel's samples, values are
+/// independent random numbers.
+pub struct Halton {
+    seed: u64,
+    index: usize,
+    dimension: usize,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let shift = to_unit(hash(self.seed, dimension as u64));
+                (radical_inverse(base, self.index as u64) + shift).fract()
+            }
+            None => to_unit(hash(hash(self.seed, self.index as u64), dimension as u64)),
+        }
+    }
+}
+
+// the columns of the generator matrix of Sobol dimension with primitive polynomial `degree`,
+// `coefficients` and initial direction numbers `m`
+const fn sobol_directions(degree: usize, coefficients: u32, m: [u32; 3]) -> [u32; 32] {
+    let mut v = [0; 32];
+    let mut k = 0;
+    while k < 32 {
+        if k < degree {
+            v[k] = m[k] << (31 - k);
+        } else {
+            v[k] = v[k - degree] ^ (v[k - degree] >> degree);
+            let mut i = 1;
+            while i < degree {
+                if (coefficients >> (degree - 1 - i)) & 1 == 1 {
+                    v[k] ^= v[k - i];
+                }
+                i += 1;
+            }
+        }
+        k += 1;
+    }
+    v
+}
+
+const fn van_der_corput() -> [u32; 32] {
+    let mut v = [0; 32];
+    let mut k = 0;
+    while k < 32 {
+        v[k] = 1 << (31 - k);
+        k += 1;
+    }
+    v
+}
+
+// the first four Sobol dimensions, with Joe and Kuo's direction numbers
+const SOBOL_DIRECTIONS: [[u32; 32]; 4] = [
+    van_der_corput(),
+    sobol_directions(1, 0, [1, 0, 0]),
+    sobol_directions(2, 1, [1, 3, 0]),
+    sobol_directions(3, 1, [1, 3, 1]),
+];
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    let mut result = 0;
+    let mut i = index;
+    for direction in SOBOL_DIRECTIONS[dimension].iter() {
+        if i == 0 {
+            break;
+        }
+        if i & 1 == 1 {
+            result ^= direction;
+        }
+        i >>= 1;
+    }
+    result
+}
+
+// Laine and Karras' hash, which only lets each bit affect the bits above it
+fn laine_karras(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+// a random nested uniform scramble, which keeps every stratum of a (t, s)-sequence intact
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras(x.reverse_bits(), seed).reverse_bits()
+}
+
+// dimension `dimension` of sample `index`. dimensions come in blocks of four, each block
+// shuffled and scrambled with its own seed, after Burley's Practical Hash-based Owen Scrambling
+fn scrambled_sobol(seed: u64, index: usize, dimension: usize) -> f64 {
+    let block = hash(seed, (dimension / 4) as u64);
+    let index = owen_scramble(index as u32, block as u32);
+    let axis = dimension % 4;
+    let x = owen_scramble(sobol(index, axis), hash(block, axis as u64) as u32);
+    x as f64 / 4_294_967_296.0
+}
+
+/// The Sobol sequence, Owen scrambled per pixel. Each power of two of a pixel's samples is
+/// stratified over every four dimensions together.
+pub struct Sobol {
+    seed: u64,
+    index: usize,
+    dimension: usize,
+}
+
+impl Sobol {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Sobol {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        scrambled_sobol(self.seed, self.index, dimension)
+    }
+}
+
+const MASK_SIZE: usize = 64;
+
+// a tiling pattern of energies, where every point casts a gaussian over its neighbours
+#[derive(Clone)]
+struct Pattern {
+    points: Vec<bool>,
+    energy: Vec<f64>,
+    kernel: Vec<f64>,
+}
+
+impl Pattern {
+    fn new(sigma: f64) -> Self {
+        let cells = MASK_SIZE * MASK_SIZE;
+        let kernel = (0..cells)
+            .map(|i| {
+                let (dx, dy) = (i % MASK_SIZE, i / MASK_SIZE);
+                let dx = dx.min(MASK_SIZE - dx) as f64;
+                let dy = dy.min(MASK_SIZE - dy) as f64;
+                (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        Self {
+            points: vec![false; cells],
+            energy: vec![0.0; cells],
+            kernel,
+        }
+    }
+
+    fn set(&mut self, cell: usize, point: bool) {
+        let sign = if point { 1.0 } else { -1.0 };
+        self.points[cell] = point;
+        let (x, y) = (cell % MASK_SIZE, cell / MASK_SIZE);
+        for (i, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (i % MASK_SIZE + MASK_SIZE - x) % MASK_SIZE;
+            let dy = (i / MASK_SIZE + MASK_SIZE - y) % MASK_SIZE;
+            *energy += sign * self.kernel[dx + dy * MASK_SIZE];
+        }
+    }
+
+    // the point with the most energy around it
+    fn tightest_cluster(&self) -> usize {
+        (0..self.points.len())
+            .filter(|&i| self.points[i])
+            .max_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+            .unwrap()
+    }
+
+    // the empty cell with the least
+    fn largest_void(&self) -> usize {
+        (0..self.points.len())
+            .filter(|&i| !self.points[i])
+            .min_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+            .unwrap()
+    }
+}
+
+// Ulichney's void-and-cluster method. cells are ranked so that every prefix of the ranking is
+// spread as evenly as it can be, which makes the normalized ranks a blue noise threshold map
+fn void_and_cluster() -> Vec<f64> {
+    let cells = MASK_SIZE * MASK_SIZE;
+    let mut pattern = Pattern::new(1.5);
+
+    // a tenth of the cells at random
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut count = 0;
+    while count < cells / 10 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let cell = (mix(state) % cells as u64) as usize;
+        if !pattern.points[cell] {
+            pattern.set(cell, true);
+            count += 1;
+        }
+    }
+    // evened out by moving points from the tightest cluster into the largest void, until that
+    // would move a point straight back
+    loop {
+        let cluster = pattern.tightest_cluster();
+        pattern.set(cluster, false);
+        let void = pattern.largest_void();
+        pattern.set(void, true);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; cells];
+    // the initial points rank lowest, taken out tightest cluster first
+    let mut thinned = pattern.clone();
+    for rank in (0..count).rev() {
+        let cluster = thinned.tightest_cluster();
+        thinned.set(cluster, false);
+        ranks[cluster] = rank;
+    }
+    // then the rest, into the largest void first. past half full that's the same as taking
+    // empty cells out of the tightest cluster of empty cells
+    for rank in count..cells {
+        let void = pattern.largest_void();
+        pattern.set(void, true);
+        ranks[void] = rank;
+    }
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f64 + 0.5) / cells as f64)
+        .collect()
+}
+
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: std::sync::OnceLock<Vec<f64>> = std::sync::OnceLock::new();
+    MASK.get_or_init(void_and_cluster)
+}
+
+/// The same scrambled Sobol samples in every pixel, each dimension shifted by a blue noise mask
+/// tiled over the image, after Georgiev and Fajardo's blue noise dithered sampling.
+///
+/// Neighbouring pixels get very different shifts, so what error is left is high frequency noise
+/// without clumps, which looks smoother at low sample counts. Every dimension reads the mask at
+/// its own random offset.
+pub struct BlueNoise {
+    seed: u64,
+    x: usize,
+    y: usize,
+    index: usize,
+    dimension: usize,
+}
+
+impl BlueNoise {
+    /// `seed` picks the sequence every pixel shares.
+    pub fn new(seed: u64, x: usize, y: usize) -> Self {
+        Self {
+            seed,
+            x,
+            y,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for BlueNoise {
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_dimension(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let offset = hash(self.seed, dimension as u64);
+        let x = (self.x + offset as usize % MASK_SIZE) % MASK_SIZE;
+        let y = (self.y + (offset >> 32) as usize % MASK_SIZE) % MASK_SIZE;
+        let shift = blue_noise_mask()[x + y * MASK_SIZE];
+        (scrambled_sobol(self.seed, self.index, dimension) + shift).fract()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 5] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ];
+
+    #[test]
+    fn grids_are_nearly_square() {
+        assert_eq!(grid(1), (1, 1));
+        assert_eq!(grid(7), (1, 7));
+        assert_eq!(grid(12), (3, 4));
+        assert_eq!(grid(16), (4, 4));
+        assert_eq!(grid(50), (5, 10));
+    }
+
+    #[test]
+    fn every_dimension_averages_a_half() {
+        for &spp in [7, 12, 16].iter() {
+            let settings = RenderSettings::default()
+                .with_resolution(64, 64)
+                .with_samples_per_pixel(spp);
+            for &kind in KINDS.iter() {
+                let settings = settings.with_sampler(kind);
+                let mut sums = [0.0; 8];
+                for (x, y) in (0..64).flat_map(|y| (0..64).map(move |x| (x, y))) {
+                    let mut sampler = settings.pixel_sampler(x, y);
+                    for i in 0..spp {
+                        sampler.start_sample(i);
+                        for sum in sums.iter_mut() {
+                            *sum += sampler.next_dimension();
+                        }
+                    }
+                }
+                for (dimension, sum) in sums.iter().enumerate() {
+                    let mean = sum / (64 * 64 * spp) as f64;
+                    assert!(
+                        (mean - 0.5).abs() < 0.02,
+                        "{:?} at {} spp averages {} in dimension {}",
+                        kind,
+                        spp,
+                        mean,
+                        dimension
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_samples_fill_every_stratum() {
+        for &count in [1, 7, 12, 16, 50].iter() {
+            let (columns, rows) = grid(count as u32);
+            let (columns, rows) = (columns as usize, rows as usize);
+            for seed in 0..8 {
+                let mut sampler = Stratified::new(seed, count);
+                let samples = (0..count)
+                    .map(|i| {
+                        sampler.start_sample(i);
+                        (0..6).map(|_| sampler.next_dimension()).collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                for pair in 0..3 {
+                    let mut cells = vec![0; count];
+                    let mut xs = vec![0; count];
+                    let mut ys = vec![0; count];
+                    for sample in samples.iter() {
+                        let (x, y) = (sample[2 * pair], sample[2 * pair + 1]);
+                        let cell =
+                            (x * columns as f64) as usize + (y * rows as f64) as usize * columns;
+                        cells[cell] += 1;
+                        xs[(x * count as f64) as usize] += 1;
+                        ys[(y * count as f64) as usize] += 1;
+                    }
+                    assert!(
+                        cells.iter().all(|&n| n == 1),
+                        "{} samples {:?}",
+                        count,
+                        cells
+                    );
+                    assert!(xs.iter().all(|&n| n == 1), "{} samples {:?}", count, xs);
+                    assert!(ys.iter().all(|&n| n == 1), "{} samples {:?}", count, ys);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,999 @@
+//! A declarative scene format, stored as [RON](https://github.com/ron-rs/ron).
+//!
+//! A `SceneDescription` is plain data that round-trips through serde. Building it turns named
+//! textures and materials into shared `Texture`s and `Material`s and objects into a `Bvh`.
+
+use super::{
+    gltf, obj, Bvh, Camera, Checker, ConstantMedium, Cuboid, Dielectric, DiffuseLight, Filter,
+    HitTable, HitTableList, ImageTexture, Ior, Lambertian, Marble, Material, Metal, MovingSphere,
+    NoiseTexture, Perlin, Principled, RenderSettings, RotateY, SolidColor, Sphere, Texture,
+    Transform, Translate, Triangle, Turbulence, UvChecker, Wood, Wrap, XYRect, XZRect, YZRect,
+};
+use cgmath::{Deg, InnerSpace, Matrix, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc as Rc;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Format(ron::Error),
+    UnknownMaterial(String),
+    UnknownTexture(String),
+    Obj {
+        path: PathBuf,
+        error: obj::Error,
+    },
+    Image {
+        path: PathBuf,
+        error: image::ImageError,
+    },
+    Gltf {
+        path: PathBuf,
+        error: gltf::Error,
+    },
+    SingularTransform,
+    DegenerateTriangle([[f64; 3]; 3]),
+    InvalidAbsorption([f64; 3]),
+    InvalidDensity(f64),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Format(err) => write!(f, "{}", err),
+            Error::UnknownMaterial(name) => write!(f, "unknown material `{}`", name),
+            Error::UnknownTexture(name) => write!(f, "unknown texture `{}`", name),
+            Error::Obj { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::Image { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::Gltf { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::SingularTransform => write!(f, "transform matrix can't be inverted"),
+            Error::DegenerateTriangle(vertices) => {
+                write!(f, "triangle {:?} has collinear vertices", vertices)
+            }
+            Error::InvalidAbsorption(absorption) => write!(
+                f,
+                "absorption {:?} must be finite and non-negative",
+                absorption
+            ),
+            Error::InvalidDensity(density) => {
+                write!(f, "density {} must be finite and positive", density)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Format(err) => Some(err),
+            Error::UnknownMaterial(_)
+            | Error::UnknownTexture(_)
+            | Error::SingularTransform
+            | Error::DegenerateTriangle(_)
+            | Error::InvalidAbsorption(_)
+            | Error::InvalidDensity(_) => None,
+            Error::Obj { error, .. } => Some(error),
+            Error::Image { error, .. } => Some(error),
+            Error::Gltf { error, .. } => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ron::Error> for Error {
+    fn from(err: ron::Error) -> Self {
+        Error::Format(err)
+    }
+}
+
+/// The arguments to `Camera::new`. Without an `aspect` the render resolution's is used.
+///
+/// The shutter defaults to an instant at time zero. Opening it blurs moving objects.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CameraDescription {
+    pub origin: [f64; 3],
+    pub look_at: [f64; 3],
+    pub up: [f64; 3],
+    pub v_fov: f64,
+    #[serde(default)]
+    pub aspect: Option<f64>,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    #[serde(default)]
+    pub shutter_open: f64,
+    #[serde(default)]
+    pub shutter_close: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TextureDescription {
+    Solid([f64; 3]),
+    /// A 3D checkerboard with cells `pi / scale` wide.
+    Checker {
+        odd: [f64; 3],
+        even: [f64; 3],
+        scale: f64,
+    },
+    /// A checkerboard over surface coordinates.
+    UvChecker {
+        odd: [f64; 3],
+        even: [f64; 3],
+        columns: f64,
+        rows: f64,
+    },
+    /// Perlin noise with features `1 / scale` apart. Textures with the same seed share a pattern.
+    Noise {
+        scale: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+    Turbulence {
+        scale: f64,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    Marble {
+        light: [f64; 3],
+        dark: [f64; 3],
+        scale: f64,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Rings around the y axis, `scale` to a unit.
+    Wood {
+        light: [f64; 3],
+        dark: [f64; 3],
+        scale: f64,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+    /// A PNG, JPEG or PPM file, relative to the scene file.
+    Image {
+        path: String,
+        #[serde(default)]
+        filter: Filter,
+        #[serde(default)]
+        wrap: Wrap,
+    },
+}
+
+fn default_octaves() -> usize {
+    7
+}
+
+/// Where a material gets its color: either inline or from a named texture.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum TextureRef {
+    Color([f64; 3]),
+    Named(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: TextureRef,
+        fuzz: f64,
+    },
+    /// Glass. `absorption` is each channel's Beer-Lambert coefficient: after a distance `d`
+    /// inside, `e^-(absorption d)` of the light is left. Clear by default.
+    Dielectric {
+        ref_idx: f64,
+        #[serde(default)]
+        absorption: [f64; 3],
+    },
+    /// Glass whose refractive index varies with wavelength, so it splits light into colors.
+    DispersiveDielectric {
+        ior: Ior<f64>,
+        #[serde(default)]
+        absorption: [f64; 3],
+    },
+    DiffuseLight {
+        emit: TextureRef,
+    },
+    /// A GGX metallic-roughness surface. `metallic` and `roughness` run from 0 to 1.
+    Principled {
+        base_color: TextureRef,
+        metallic: f64,
+        roughness: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    /// A sphere at `center0` at `time0`, moving steadily to `center1` at `time1`.
+    MovingSphere {
+        center0: [f64; 3],
+        center1: [f64; 3],
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: String,
+    },
+    /// A rectangle at `z = k`, facing +z unless `flip`ped.
+    XYRect {
+        x: [f64; 2],
+        y: [f64; 2],
+        k: f64,
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    /// A rectangle at `y = k`, facing +y unless `flip`ped.
+    XZRect {
+        x: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    /// A rectangle at `x = k`, facing +x unless `flip`ped.
+    YZRect {
+        y: [f64; 2],
+        z: [f64; 2],
+        k: f64,
+        material: String,
+        #[serde(default)]
+        flip: bool,
+    },
+    Cuboid {
+        min: [f64; 3],
+        max: [f64; 3],
+        material: String,
+    },
+    /// An `.obj` file, relative to the scene file. Its materials come from its own `.mtl`.
+    Obj { path: String },
+    /// The default scene of a `.gltf` or `.glb` file, relative to the scene file. Its materials
+    /// come from the file and its cameras are ignored.
+    Gltf { path: String },
+    /// Smoke or fog filling `boundary`, which has to be closed. `density` is how likely a ray is
+    /// to scatter per unit length, and has to be positive.
+    ConstantMedium {
+        density: f64,
+        albedo: TextureRef,
+        boundary: Box<ObjectDescription>,
+    },
+    Translate {
+        offset: [f64; 3],
+        object: Box<ObjectDescription>,
+    },
+    /// Turns `object` about the y axis, by the right hand rule.
+    RotateY {
+        degrees: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// Applies an affine matrix, written out row by row.
+    Transform {
+        matrix: [[f64; 4]; 4],
+        object: Box<ObjectDescription>,
+    },
+}
+
+/// What a ray that escapes the scene sees.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Sky {
+    Solid([f64; 3]),
+    /// Blends from `horizon` to `zenith` with the ray's height.
+    Gradient {
+        horizon: [f64; 3],
+        zenith: [f64; 3],
+    },
+}
+
+impl Sky {
+    pub fn color(&self, direction: &Vector3<f64>) -> Vector3<f64> {
+        match self {
+            Sky::Solid(c) => (*c).into(),
+            Sky::Gradient { horizon, zenith } => {
+                let unit_direction = direction.normalize();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                (1.0 - t) * Vector3::from(*horizon) + t * Vector3::from(*zenith)
+            }
+        }
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::Gradient {
+            horizon: [1.0, 1.0, 1.0],
+            zenith: [0.5, 0.7, 1.0],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub sky: Sky,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default)]
+    pub textures: BTreeMap<String, TextureDescription>,
+    pub materials: BTreeMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+}
+
+impl std::str::FromStr for SceneDescription {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ron::from_str(s)?)
+    }
+}
+
+impl SceneDescription {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// A scene of just a glTF file, seen through its first perspective camera. The resolution is
+    /// fitted to the camera's aspect ratio if it has one, and from then on the camera follows the
+    /// resolution, so overriding it widens or narrows the view rather than stretching the image.
+    ///
+    /// Build it with the file's directory.
+    pub fn from_gltf<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let error = |error| Error::Gltf {
+            path: path.to_owned(),
+            error,
+        };
+        let mut camera = gltf::cameras(path)
+            .map_err(error)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| error(gltf::Error::NoCamera))?;
+        let mut render = RenderSettings::default();
+        if let Some(aspect) = camera.aspect.take() {
+            let height = (render.width as f64 / aspect).round().max(1.0);
+            render = render.with_resolution(render.width, height as usize);
+        }
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(Self {
+            camera,
+            sky: Sky::default(),
+            render,
+            textures: BTreeMap::new(),
+            materials: BTreeMap::new(),
+            objects: vec![ObjectDescription::Gltf {
+                path: file.into_owned(),
+            }],
+        })
+    }
+
+    pub fn to_string(&self) -> Result<String, Error> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::new(),
+        )?)
+    }
+
+    /// The final scene from "Ray Tracing in One Weekend": a field of small random spheres
+    /// around three big ones.
+    pub fn one_weekend<R: rand::Rng>(rng: &mut R) -> Self {
+        let mut materials = BTreeMap::new();
+        let mut objects = vec![];
+        let mut sphere = |center, radius, name: String, m| {
+            materials.insert(name.clone(), m);
+            objects.push(ObjectDescription::Sphere {
+                center,
+                radius,
+                material: name,
+            });
+        };
+
+        sphere(
+            [0.0, -1000.0, 0.0],
+            1000.0,
+            "ground".to_owned(),
+            MaterialDescription::Lambertian {
+                albedo: TextureRef::Color([0.5, 0.5, 0.5]),
+            },
+        );
+        for a in -11..11 {
+            for b in -11..11 {
+                let choose_mat = rng.gen::<f64>();
+                let center = [
+                    (a as f64) + 0.9 * rng.gen::<f64>(),
+                    0.2,
+                    (b as f64) + 0.9 * rng.gen::<f64>(),
+                ];
+                let material = if choose_mat < 0.8 {
+                    MaterialDescription::Lambertian {
+                        albedo: TextureRef::Color([
+                            rng.gen::<f64>() * rng.gen::<f64>(),
+                            rng.gen::<f64>() * rng.gen::<f64>(),
+                            rng.gen::<f64>() * rng.gen::<f64>(),
+                        ]),
+                    }
+                } else if choose_mat < 0.95 {
+                    MaterialDescription::Metal {
+                        albedo: TextureRef::Color([
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                        ]),
+                        fuzz: 0.5 * rng.gen::<f64>(),
+                    }
+                } else {
+                    MaterialDescription::Dielectric {
+                        ref_idx: 1.5,
+                        absorption: [0.0; 3],
+                    }
+                };
+                sphere(center, 0.2, format!("small_{}_{}", a, b), material);
+            }
+        }
+        sphere(
+            [0.0, 1.0, 0.0],
+            1.0,
+            "glass".to_owned(),
+            MaterialDescription::Dielectric {
+                ref_idx: 1.5,
+                absorption: [0.0; 3],
+            },
+        );
+        sphere(
+            [-4.0, 1.0, 0.0],
+            1.0,
+            "matte".to_owned(),
+            MaterialDescription::Lambertian {
+                albedo: TextureRef::Color([0.4, 0.2, 0.1]),
+            },
+        );
+        sphere(
+            [4.0, 1.0, 0.0],
+            1.0,
+            "mirror".to_owned(),
+            MaterialDescription::Metal {
+                albedo: TextureRef::Color([0.7, 0.6, 0.5]),
+                fuzz: 0.0,
+            },
+        );
+
+        Self {
+            camera: CameraDescription {
+                origin: [13.0, 2.0, 3.0],
+                look_at: [0.0, 0.0, 0.0],
+                up: [0.0, 1.0, 0.0],
+                v_fov: 20.0,
+                aspect: None,
+                aperture: 0.1,
+                focus_dist: 10.0,
+                shutter_open: 0.0,
+                shutter_close: 0.0,
+            },
+            sky: Sky::default(),
+            render: RenderSettings::default(),
+            textures: BTreeMap::new(),
+            materials,
+            objects,
+        }
+    }
+}
+
+pub struct Scene<R> {
+    pub world: Bvh<f64, R>,
+    /// Everything in `world` with an emissive material, placed the same way, to aim rays at.
+    pub lights: HitTableList<f64, R>,
+    pub camera: Camera<f64>,
+    pub sky: Sky,
+    pub render: RenderSettings,
+}
+
+impl<R: rand::Rng + 'static> std::str::FromStr for Scene<R> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_description(&s.parse()?, Path::new(""))
+    }
+}
+
+impl<R: rand::Rng + 'static> Scene<R> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let description = SceneDescription::from_path(path)?;
+        Self::from_description(&description, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// Files referenced by the description are resolved relative to `dir`.
+    pub fn from_description(description: &SceneDescription, dir: &Path) -> Result<Self, Error> {
+        let solid = |c: &[f64; 3]| -> Rc<dyn Texture<f64> + Send + Sync> {
+            Rc::new(SolidColor::new((*c).into()))
+        };
+        let mut noise = BTreeMap::new();
+        let mut perlin = |seed: u64| {
+            Rc::clone(
+                noise
+                    .entry(seed)
+                    .or_insert_with(|| Rc::new(Perlin::new(seed))),
+            )
+        };
+        let mut textures = BTreeMap::new();
+        for (name, t) in description.textures.iter() {
+            let t: Rc<dyn Texture<f64> + Send + Sync> = match t {
+                TextureDescription::Solid(c) => solid(c),
+                TextureDescription::Checker { odd, even, scale } => {
+                    Rc::new(Checker::new(solid(odd), solid(even), *scale))
+                }
+                TextureDescription::UvChecker {
+                    odd,
+                    even,
+                    columns,
+                    rows,
+                } => Rc::new(UvChecker::new(solid(odd), solid(even), *columns, *rows)),
+                TextureDescription::Noise { scale, seed } => {
+                    Rc::new(NoiseTexture::new(perlin(*seed), *scale))
+                }
+                TextureDescription::Turbulence {
+                    scale,
+                    octaves,
+                    seed,
+                } => Rc::new(Turbulence::new(perlin(*seed), *scale, *octaves)),
+                TextureDescription::Marble {
+                    light,
+                    dark,
+                    scale,
+                    octaves,
+                    seed,
+                } => Rc::new(Marble::new(
+                    perlin(*seed),
+                    (*light).into(),
+                    (*dark).into(),
+                    *scale,
+                    *octaves,
+                )),
+                TextureDescription::Wood {
+                    light,
+                    dark,
+                    scale,
+                    octaves,
+                    seed,
+                } => Rc::new(Wood::new(
+                    perlin(*seed),
+                    (*light).into(),
+                    (*dark).into(),
+                    *scale,
+                    *octaves,
+                )),
+                TextureDescription::Image { path, filter, wrap } => {
+                    let path = dir.join(path);
+                    match ImageTexture::from_path(&path) {
+                        Ok(image) => Rc::new(image.with_filter(*filter).with_wrap(*wrap)),
+                        Err(error) => return Err(Error::Image { path, error }),
+                    }
+                }
+            };
+            textures.insert(name.as_str(), t);
+        }
+        let texture = |t: &TextureRef| match t {
+            TextureRef::Color(c) => Ok(solid(c)),
+            TextureRef::Named(name) => textures
+                .get(name.as_str())
+                .map(Rc::clone)
+                .ok_or_else(|| Error::UnknownTexture(name.clone())),
+        };
+
+        let check = |absorption: &[f64; 3]| {
+            if absorption.iter().all(|a| a.is_finite() && *a >= 0.0) {
+                Ok((*absorption).into())
+            } else {
+                Err(Error::InvalidAbsorption(*absorption))
+            }
+        };
+
+        let mut materials = BTreeMap::new();
+        for (name, m) in description.materials.iter() {
+            let m: Rc<dyn Material<f64, R> + Send + Sync> = match m {
+                MaterialDescription::Lambertian { albedo } => {
+                    Rc::new(Lambertian::textured(texture(albedo)?))
+                }
+                MaterialDescription::Metal { albedo, fuzz } => {
+                    Rc::new(Metal::textured(texture(albedo)?, *fuzz))
+                }
+                MaterialDescription::Dielectric {
+                    ref_idx,
+                    absorption,
+                } => Rc::new(Dielectric::new(*ref_idx).with_absorption(check(absorption)?)),
+                MaterialDescription::DispersiveDielectric { ior, absorption } => {
+                    Rc::new(Dielectric::dispersive(*ior).with_absorption(check(absorption)?))
+                }
+                MaterialDescription::DiffuseLight { emit } => {
+                    Rc::new(DiffuseLight::textured(texture(emit)?))
+                }
+                MaterialDescription::Principled {
+                    base_color,
+                    metallic,
+                    roughness,
+                } => Rc::new(Principled::textured(
+                    texture(base_color)?,
+                    *metallic,
+                    *roughness,
+                )),
+            };
+            materials.insert(name.as_str(), m);
+        }
+        let material = |name: &String| {
+            materials
+                .get(name.as_str())
+                .map(Rc::clone)
+                .ok_or_else(|| Error::UnknownMaterial(name.clone()))
+        };
+
+        let time = description.camera.shutter_open..description.camera.shutter_close;
+        let mut list = vec![];
+        let mut emitters = vec![];
+        for object in description.objects.iter() {
+            let time = time.clone();
+            add_object(
+                object,
+                &material,
+                &texture,
+                dir,
+                time,
+                &mut list,
+                &mut emitters,
+            )?;
+        }
+        let mut lights = HitTableList::new();
+        emitters.into_iter().for_each(|l| lights.add(l));
+
+        let render = description.render;
+        let c = &description.camera;
+        let camera = Camera::new(
+            c.origin.into(),
+            c.look_at.into(),
+            c.up.into(),
+            c.v_fov,
+            c.aspect.unwrap_or_else(|| render.aspect()),
+            c.aperture,
+            c.focus_dist,
+        )
+        .with_shutter(c.shutter_open, c.shutter_close);
+
+        Ok(Self {
+            world: Bvh::new(list, time),
+            lights,
+            camera,
+            sky: description.sky.clone(),
+            render,
+        })
+    }
+}
+
+type Object<R> = Box<dyn HitTable<f64, R> + Send + Sync>;
+type Shared<R> = Rc<dyn HitTable<f64, R> + Send + Sync>;
+
+// adds a surface to the world, and to the lights too if it glows
+fn add_surface<R: 'static>(
+    object: Object<R>,
+    glows: bool,
+    list: &mut Vec<Object<R>>,
+    lights: &mut Vec<Object<R>>,
+) {
+    if glows {
+        let object: Shared<R> = Rc::from(object);
+        lights.push(Box::new(Rc::clone(&object)));
+        list.push(Box::new(object));
+    } else {
+        list.push(object);
+    }
+}
+
+// builds `object` into `list`, and whatever parts of it glow into `lights` as well
+fn add_object<R, F, G>(
+    object: &ObjectDescription,
+    material: &F,
+    texture: &G,
+    dir: &Path,
+    time: Range<f64>,
+    list: &mut Vec<Object<R>>,
+    lights: &mut Vec<Object<R>>,
+) -> Result<(), Error>
+where
+    R: rand::Rng + 'static,
+    F: Fn(&String) -> Result<Rc<dyn Material<f64, R> + Send + Sync>, Error>,
+    G: Fn(&TextureRef) -> Result<Rc<dyn Texture<f64> + Send + Sync>, Error>,
+{
+    // builds the wrapped object, grouping it first if it made more than one hittable, and its
+    // lights, for the wrapper to place the same way
+    let inner = |object: &ObjectDescription| -> Result<(Shared<R>, Vec<Shared<R>>), Error> {
+        let (mut objects, mut lights) = (vec![], vec![]);
+        add_object(
+            object,
+            material,
+            texture,
+            dir,
+            time.clone(),
+            &mut objects,
+            &mut lights,
+        )?;
+        let object = if objects.len() == 1 {
+            Rc::from(objects.pop().unwrap())
+        } else {
+            Rc::new(Bvh::new(objects, time.clone())) as Shared<R>
+        };
+        Ok((object, lights.into_iter().map(Rc::from).collect()))
+    };
+    match object {
+        ObjectDescription::Sphere {
+            center,
+            radius,
+            material: name,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let sphere = Sphere::new((*center).into(), *radius, m);
+            add_surface(Box::new(sphere), glows, list, lights);
+        }
+        ObjectDescription::MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material: name,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let sphere = MovingSphere::new(
+                (*center0).into(),
+                (*center1).into(),
+                *time0,
+                *time1,
+                *radius,
+                m,
+            );
+            add_surface(Box::new(sphere), glows, list, lights);
+        }
+        ObjectDescription::Triangle {
+            vertices: vertices @ [v0, v1, v2],
+            material: name,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let triangle = Triangle::new((*v0).into(), (*v1).into(), (*v2).into(), m)
+                .ok_or(Error::DegenerateTriangle(*vertices))?;
+            add_surface(Box::new(triangle), glows, list, lights);
+        }
+        ObjectDescription::XYRect {
+            x,
+            y,
+            k,
+            material: name,
+            flip,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let rect = XYRect::new(x[0]..x[1], y[0]..y[1], *k, m);
+            let rect = if *flip { rect.flipped() } else { rect };
+            add_surface(Box::new(rect), glows, list, lights);
+        }
+        ObjectDescription::XZRect {
+            x,
+            z,
+            k,
+            material: name,
+            flip,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let rect = XZRect::new(x[0]..x[1], z[0]..z[1], *k, m);
+            let rect = if *flip { rect.flipped() } else { rect };
+            add_surface(Box::new(rect), glows, list, lights);
+        }
+        ObjectDescription::YZRect {
+            y,
+            z,
+            k,
+            material: name,
+            flip,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let rect = YZRect::new(y[0]..y[1], z[0]..z[1], *k, m);
+            let rect = if *flip { rect.flipped() } else { rect };
+            add_surface(Box::new(rect), glows, list, lights);
+        }
+        ObjectDescription::Cuboid {
+            min,
+            max,
+            material: name,
+        } => {
+            let m = material(name)?;
+            let glows = m.is_emissive();
+            let cuboid = Cuboid::new((*min).into(), (*max).into(), m);
+            add_surface(Box::new(cuboid), glows, list, lights);
+        }
+        // the boundary only shapes the medium, so nothing it's made of glows
+        ObjectDescription::ConstantMedium {
+            density,
+            albedo,
+            boundary,
+        } => {
+            if !(density.is_finite() && *density > 0.0) {
+                return Err(Error::InvalidDensity(*density));
+            }
+            list.push(Box::new(ConstantMedium::textured(
+                inner(boundary)?.0,
+                *density,
+                texture(albedo)?,
+            )))
+        }
+        ObjectDescription::Translate { offset, object } => {
+            let (object, inner_lights) = inner(object)?;
+            let offset = (*offset).into();
+            list.push(Box::new(Translate::new(object, offset)));
+            for light in inner_lights {
+                lights.push(Box::new(Translate::new(light, offset)));
+            }
+        }
+        ObjectDescription::RotateY { degrees, object } => {
+            let (object, inner_lights) = inner(object)?;
+            list.push(Box::new(RotateY::new(object, Deg(*degrees))));
+            for light in inner_lights {
+                lights.push(Box::new(RotateY::new(light, Deg(*degrees))));
+            }
+        }
+        ObjectDescription::Transform { matrix, object } => {
+            let matrix = Matrix4::from(*matrix).transpose();
+            let (object, inner_lights) = inner(object)?;
+            let transform = Transform::new(object, matrix).ok_or(Error::SingularTransform)?;
+            list.push(Box::new(transform));
+            for light in inner_lights {
+                let light = Transform::new(light, matrix).ok_or(Error::SingularTransform)?;
+                lights.push(Box::new(light));
+            }
+        }
+        ObjectDescription::Obj { path } => {
+            let path = dir.join(path);
+            let meshes = obj::load(&path).map_err(|error| Error::Obj { path, error })?;
+            for mesh in meshes {
+                let glows = mesh.material().is_emissive();
+                add_surface(Box::new(mesh), glows, list, lights);
+            }
+        }
+        ObjectDescription::Gltf { path } => {
+            let path = dir.join(path);
+            let gltf = gltf::load(&path).map_err(|error| Error::Gltf { path, error })?;
+            list.extend(gltf.objects);
+            lights.extend(gltf.lights);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+
+    #[test]
+    fn one_weekend_round_trips() {
+        let description = SceneDescription::one_weekend(&mut SmallRng::seed_from_u64(0));
+        let text = description.to_string().unwrap();
+        let parsed: SceneDescription = text.parse().unwrap();
+        assert_eq!(parsed, description);
+        assert_eq!(parsed.to_string().unwrap(), text);
+    }
+
+    #[test]
+    fn missing_sections_default() {
+        let text = r#"(
+            camera: (origin: (0, 0, 1), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 90,
+                     aperture: 0, focus_dist: 1),
+            materials: { "white": Lambertian(albedo: (1, 1, 1)) },
+            objects: [Sphere(center: (0, 0, 0), radius: 0.5, material: "white")],
+        )"#;
+        let description: SceneDescription = text.parse().unwrap();
+        assert_eq!(description.render, RenderSettings::default());
+        assert_eq!(description.sky, Sky::default());
+        assert!(Scene::<SmallRng>::from_description(&description, Path::new("")).is_ok());
+    }
+
+    #[test]
+    fn unknown_materials_are_errors() {
+        let text = r#"(
+            camera: (origin: (0, 0, 1), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 90,
+                     aperture: 0, focus_dist: 1),
+            materials: {},
+            objects: [Sphere(center: (0, 0, 0), radius: 0.5, material: "missing")],
+        )"#;
+        let description: SceneDescription = text.parse().unwrap();
+        match Scene::<SmallRng>::from_description(&description, Path::new("")) {
+            Err(Error::UnknownMaterial(name)) => assert_eq!(name, "missing"),
+            _ => panic!("expected an unknown material"),
+        }
+    }
+
+    #[test]
+    fn negative_absorption_is_an_error() {
+        let text = r#"(
+            camera: (origin: (0, 0, 1), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 90,
+                     aperture: 0, focus_dist: 1),
+            materials: {"glass": Dielectric(ref_idx: 1.5, absorption: (0.5, -1, 0))},
+            objects: [Sphere(center: (0, 0, 0), radius: 0.5, material: "glass")],
+        )"#;
+        let description: SceneDescription = text.parse().unwrap();
+        match Scene::<SmallRng>::from_description(&description, Path::new("")) {
+            Err(Error::InvalidAbsorption(absorption)) => assert_eq!(absorption, [0.5, -1.0, 0.0]),
+            _ => panic!("expected invalid absorption"),
+        }
+    }
+
+    #[test]
+    fn non_positive_density_is_an_error() {
+        let text = r#"(
+            camera: (origin: (0, 0, 1), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 90,
+                     aperture: 0, focus_dist: 1),
+            materials: {"white": Lambertian(albedo: (1, 1, 1))},
+            objects: [ConstantMedium(density: 0, albedo: (1, 1, 1),
+                boundary: Sphere(center: (0, 0, 0), radius: 0.5, material: "white"))],
+        )"#;
+        let description: SceneDescription = text.parse().unwrap();
+        match Scene::<SmallRng>::from_description(&description, Path::new("")) {
+            Err(Error::InvalidDensity(density)) => assert_eq!(density, 0.0),
+            _ => panic!("expected invalid density"),
+        }
+    }
+
+    #[test]
+    fn lights_are_found_inside_wrappers() {
+        let text = r#"(
+            camera: (origin: (0, 0, 1), look_at: (0, 0, 0), up: (0, 1, 0), v_fov: 90,
+                     aperture: 0, focus_dist: 1),
+            materials: {
+                "lamp": DiffuseLight(emit: (4, 4, 4)),
+                "glow": Principled(base_color: (0, 0, 0), metallic: 0, roughness: 1),
+                "white": Lambertian(albedo: (1, 1, 1)),
+            },
+            objects: [
+                Cuboid(min: (0, 0, 0), max: (1, 1, 1), material: "lamp"),
+                MovingSphere(center0: (0, 0, 0), center1: (1, 0, 0), time0: 0, time1: 1,
+                             radius: 0.5, material: "lamp"),
+                Translate(offset: (0, 1, 0), object: RotateY(degrees: 30,
+                    object: Sphere(center: (0, 0, 0), radius: 0.5, material: "lamp"))),
+                Transform(matrix: ((2, 0, 0, 0), (0, 1, 0, 0), (0, 0, 1, 0), (0, 0, 0, 1)),
+                    object: XYRect(x: (0, 1), y: (0, 1), k: 0, material: "lamp")),
+                Sphere(center: (0, 0, 0), radius: 0.5, material: "white"),
+                Sphere(center: (0, 0, 0), radius: 0.5, material: "glow"),
+                ConstantMedium(density: 1, albedo: (1, 1, 1),
+                    boundary: Sphere(center: (0, 0, 0), radius: 0.5, material: "lamp")),
+            ],
+        )"#;
+        let description: SceneDescription = text.parse().unwrap();
+        let scene = Scene::<SmallRng>::from_description(&description, Path::new("")).unwrap();
+        assert_eq!(scene.lights.len(), 4);
+    }
+}
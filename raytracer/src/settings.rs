@@ -0,0 +1,150 @@
+use super::integrator::{DebugView, IntegratorKind};
+use super::sampler::{Sampler, SamplerKind};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidSettings {
+    EmptyImage,
+    NoSamples,
+    NoBounces,
+    TMin(f64),
+    Gamma(f64),
+    Distance(f64),
+}
+
+impl std::fmt::Display for InvalidSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidSettings::EmptyImage => write!(f, "width and height must be non-zero"),
+            InvalidSettings::NoSamples => write!(f, "samples per pixel must be non-zero"),
+            InvalidSettings::NoBounces => write!(f, "max depth must be non-zero"),
+            InvalidSettings::TMin(t) => {
+                write!(f, "t_min must be finite and non-negative, got {}", t)
+            }
+            InvalidSettings::Gamma(g) => write!(f, "gamma must be finite and positive, got {}", g),
+            InvalidSettings::Distance(d) => write!(
+                f,
+                "integrator distances must be finite and positive, got {}",
+                d
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidSettings {}
+
+/// How an image gets rendered, independent of what's in it.
+///
+/// Missing fields deserialize to their defaults.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    /// How far along a bounced ray to start looking for hits, to avoid shadow acne.
+    pub t_min: f64,
+    pub seed: u64,
+    pub gamma: f64,
+    pub integrator: IntegratorKind,
+    pub sampler: SamplerKind,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 720,
+            height: 480,
+            samples_per_pixel: 50,
+            max_depth: 50,
+            t_min: 0.001,
+            seed: 0,
+            gamma: 2.0,
+            integrator: IntegratorKind::default(),
+            sampler: SamplerKind::default(),
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn with_resolution(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_t_min(mut self, t_min: f64) -> Self {
+        self.t_min = t_min;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_gamma(mut self, gamma: f64) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: IntegratorKind) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidSettings> {
+        let distance = match self.integrator {
+            IntegratorKind::AmbientOcclusion { distance } => Some(distance),
+            IntegratorKind::Debug(DebugView::Depth { far }) => Some(far),
+            _ => None,
+        };
+        if self.width == 0 || self.height == 0 {
+            Err(InvalidSettings::EmptyImage)
+        } else if self.samples_per_pixel == 0 {
+            Err(InvalidSettings::NoSamples)
+        } else if self.max_depth == 0 {
+            Err(InvalidSettings::NoBounces)
+        } else if !self.t_min.is_finite() || self.t_min < 0.0 {
+            Err(InvalidSettings::TMin(self.t_min))
+        } else if !self.gamma.is_finite() || self.gamma <= 0.0 {
+            Err(InvalidSettings::Gamma(self.gamma))
+        } else if let Some(d) = distance.filter(|d| !d.is_finite() || *d <= 0.0) {
+            Err(InvalidSettings::Distance(d))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn aspect(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+
+    /// Seeds the rng for one pass over one pixel, so renders are reproducible no matter how the
+    /// work is split between threads.
+    pub fn pixel_seed(&self, x: usize, y: usize, pass: usize) -> u64 {
+        let index = (x + self.width * y) as u64;
+        self.seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ ((pass as u64) << 32) ^ index
+    }
+
+    /// The sampler for every pass over one pixel.
+    pub fn pixel_sampler(&self, x: usize, y: usize) -> Box<dyn Sampler> {
+        self.sampler.build(self, x, y)
+    }
+}
@@ -0,0 +1,225 @@
+//! Wavelengths of light, and converting between them, CIE XYZ and linear RGB.
+//!
+//! The renderer works in RGB until a path meets something that treats wavelengths differently,
+//! like dispersive glass. Every camera ray carries a hero wavelength and a few companions spread
+//! evenly from it. From the first dispersive surface on, the light a path finds is estimated at
+//! each of them and added up as XYZ, which only becomes RGB once samples are averaged.
+
+use cgmath::{vec3, ElementWise, Vector3};
+use std::ops::AddAssign;
+
+/// The visible range, in nanometres.
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 780.0;
+
+// the mean of `xyz_to_rgb(cie_xyz(wavelength))` over the visible range
+const MEAN_RGB: [f64; 3] = [0.320_906_7, 0.253_871_6, 0.242_623_9];
+
+// a gaussian with a different spread on either side of its peak
+fn lobe(wavelength: f64, peak: f64, below: f64, above: f64) -> f64 {
+    let spread = if wavelength < peak { below } else { above };
+    let t = (wavelength - peak) / spread;
+    (-0.5 * t * t).exp()
+}
+
+/// The CIE 1931 standard observer's color matching functions, by Wyman, Sloan and Shirley's
+/// multi-lobe fit.
+pub fn cie_xyz(wavelength: f64) -> Vector3<f64> {
+    let l = wavelength;
+    vec3(
+        1.056 * lobe(l, 599.8, 37.9, 31.0) + 0.362 * lobe(l, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(l, 501.1, 20.4, 26.2),
+        0.821 * lobe(l, 568.8, 46.9, 40.5) + 0.286 * lobe(l, 530.9, 16.3, 31.1),
+        1.217 * lobe(l, 437.0, 11.8, 36.0) + 0.681 * lobe(l, 459.0, 26.0, 13.8),
+    )
+}
+
+/// CIE XYZ to linear sRGB, with a D65 white point.
+pub fn xyz_to_rgb(xyz: Vector3<f64>) -> Vector3<f64> {
+    vec3(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// Maps `u` from `0..1` evenly onto the visible range.
+pub fn sample_wavelength(u: f64) -> f64 {
+    MIN_WAVELENGTH + u * (MAX_WAVELENGTH - MIN_WAVELENGTH)
+}
+
+/// How many wavelengths a ray carries.
+pub const WAVELENGTHS: usize = 4;
+
+// where an RGB color is taken to be blue, green and red, for reading it at a single wavelength
+const BLUE_BELOW: f64 = 490.0;
+const GREEN_BELOW: f64 = 590.0;
+
+/// The wavelengths a path is followed at, in nanometres, and what each one's light is worth.
+///
+/// The first is the hero: where the wavelengths would go different ways, like through dispersive
+/// glass, the path follows the hero and the rest drop out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Wavelengths<T> {
+    wavelengths: [T; WAVELENGTHS],
+    weights: [T; WAVELENGTHS],
+    dispersed: bool,
+}
+
+impl<T: cgmath::BaseFloat> Wavelengths<T> {
+    /// A hero at `sample_wavelength(u)`, with companions stratified evenly across the visible
+    /// range from it.
+    pub fn sample(u: T) -> Self {
+        let u = u.to_f64().unwrap();
+        let mut wavelengths = [T::zero(); WAVELENGTHS];
+        for (i, wavelength) in wavelengths.iter_mut().enumerate() {
+            let offset = (u + i as f64 / WAVELENGTHS as f64).fract();
+            *wavelength = T::from(sample_wavelength(offset)).unwrap();
+        }
+        Self {
+            wavelengths,
+            weights: [T::one(); WAVELENGTHS],
+            dispersed: false,
+        }
+    }
+
+    pub fn hero(&self) -> T {
+        self.wavelengths[0]
+    }
+
+    pub fn get(&self) -> &[T; WAVELENGTHS] {
+        &self.wavelengths
+    }
+
+    /// Whether the path has met something that treats its wavelengths differently. Until then
+    /// the light it finds stays RGB.
+    pub fn is_dispersed(&self) -> bool {
+        self.dispersed
+    }
+
+    /// After a choice made with the hero's odds, `f(hero)`, weights every wavelength by how
+    /// likely the same choice was for it instead.
+    pub fn reweighted<F: Fn(T) -> T>(mut self, f: F) -> Self {
+        let hero = f(self.hero());
+        for (weight, wavelength) in self.weights.iter_mut().zip(self.wavelengths.iter()) {
+            *weight *= f(*wavelength) / hero;
+        }
+        self.dispersed = true;
+        self
+    }
+
+    /// Drops every wavelength but the hero, which then stands in for all of them.
+    pub fn terminate_secondary(mut self) -> Self {
+        // the hero only takes over their share once
+        if self.weights.iter().skip(1).any(|weight| !weight.is_zero()) {
+            self.weights[0] *= T::from(WAVELENGTHS).unwrap();
+        }
+        for weight in self.weights.iter_mut().skip(1) {
+            *weight = T::zero();
+        }
+        self.dispersed = true;
+        self
+    }
+
+    /// An estimate of the XYZ of `rgb` light found by a path at these wavelengths. An RGB color
+    /// is read as blue, green or red depending on where a wavelength falls, so white light
+    /// averages back out to white.
+    pub fn to_xyz(&self, rgb: Vector3<f64>) -> Vector3<f64> {
+        let xyz = self.wavelengths.iter().zip(self.weights.iter()).fold(
+            vec3(0.0, 0.0, 0.0),
+            |xyz, (wavelength, weight)| {
+                let wavelength = wavelength.to_f64().unwrap();
+                let value = if wavelength < BLUE_BELOW {
+                    rgb.z
+                } else if wavelength < GREEN_BELOW {
+                    rgb.y
+                } else {
+                    rgb.x
+                };
+                xyz + cie_xyz(wavelength) * (weight.to_f64().unwrap() * value)
+            },
+        );
+        xyz / WAVELENGTHS as f64
+    }
+}
+
+/// Light arriving along a camera ray: RGB from paths that stayed RGB, and XYZ from paths that
+/// were split into wavelengths. Sums of samples stay apart until they're resolved.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Radiance {
+    pub rgb: Vector3<f64>,
+    pub xyz: Vector3<f64>,
+}
+
+impl Radiance {
+    /// Adds `rgb` light found by a path at `wavelengths`, if it has any.
+    pub fn add(&mut self, wavelengths: Option<&Wavelengths<f64>>, rgb: Vector3<f64>) {
+        match wavelengths {
+            Some(wavelengths) if wavelengths.is_dispersed() => self.xyz += wavelengths.to_xyz(rgb),
+            _ => self.rgb += rgb,
+        }
+    }
+
+    /// Both parts as linear RGB. The XYZ is balanced so that light with a flat spectrum comes
+    /// out white.
+    pub fn to_rgb(&self) -> Vector3<f64> {
+        self.rgb + xyz_to_rgb(self.xyz).div_element_wise(Vector3::from(MEAN_RGB))
+    }
+}
+
+impl From<Vector3<f64>> for Radiance {
+    fn from(rgb: Vector3<f64>) -> Self {
+        Self {
+            rgb,
+            xyz: vec3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Default for Radiance {
+    fn default() -> Self {
+        Self::from(vec3(0.0, 0.0, 0.0))
+    }
+}
+
+impl AddAssign for Radiance {
+    fn add_assign(&mut self, other: Self) {
+        self.rgb += other.rgb;
+        self.xyz += other.xyz;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn white_light_stays_white() {
+        let n = 10_000;
+        let (white, red) = (vec3(1.0, 1.0, 1.0), vec3(0.8, 0.1, 0.1));
+        let mut sum = Radiance::default();
+        for i in 0..n {
+            let wavelengths = Wavelengths::sample((i as f64 + 0.5) / n as f64);
+            // in and back out of a prism
+            let split = wavelengths.terminate_secondary().terminate_secondary();
+            sum.add(Some(&split), white);
+            // colors that never meet anything dispersive stay as they are
+            sum.add(Some(&wavelengths), red);
+        }
+        let rgb = sum.to_rgb() / n as f64;
+        assert!((rgb - (white + red)).magnitude() < 1e-3, "{:?}", rgb);
+    }
+
+    #[test]
+    fn companions_are_spread_evenly() {
+        let wavelengths = Wavelengths::sample(0.9);
+        let mut sorted = *wavelengths.get();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let step = (MAX_WAVELENGTH - MIN_WAVELENGTH) / WAVELENGTHS as f64;
+        for pair in sorted.windows(2) {
+            assert!((pair[1] - pair[0] - step).abs() < 1e-9);
+        }
+        assert_eq!(wavelengths.hero(), sample_wavelength(0.9));
+    }
+}
@@ -1,7 +1,9 @@
 extern crate cgmath;
 
-use super::{HitRecord, HitTable, Material, Ray};
-use cgmath::InnerSpace;
+use super::{Aabb, HitRecord, HitTable, Material, Onb, Ray};
+use cgmath::{vec2, vec3, InnerSpace, Vector2};
+use rand::distributions::Standard;
+use rand::prelude::*;
 use std::ops::Range;
 use std::sync::Arc as Rc;
 
@@ -26,32 +28,230 @@ impl<T, R> Sphere<T, R> {
     }
 }
 
-impl<T: cgmath::BaseNum, R> Sphere<T, R> {
+/// Maps a point on the unit sphere to `u` around the y axis, starting from -x, and `v` up from
+/// the bottom.
+fn sphere_uv<T: cgmath::BaseFloat>(p: &cgmath::Vector3<T>) -> Vector2<T> {
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let phi = (-p.z).atan2(p.x) + pi;
+    let theta = (-p.y).max(-T::one()).min(T::one()).acos();
+    vec2(phi / (pi + pi), theta / pi)
+}
+
+// the nearest root of the ray-sphere quadratic inside `t`, if any
+fn intersect<T: cgmath::BaseFloat>(
+    center: cgmath::Vector3<T>,
+    radius: T,
+    r: &Ray<T>,
+    t: Range<T>,
+) -> Option<T> {
+    let oc = r.origin() - center;
+    let a = r.direction().magnitude2();
+    let b = oc.dot(*r.direction());
+    let c = oc.magnitude2() - radius * radius;
+    let discriminant = b * b - a * c;
+    // todo: making 0 a constant would be an improvement https://github.com/rust-num/num-traits/issues/54
+    if discriminant > T::zero() {
+        let temp = (-b - discriminant.sqrt()) / a;
+        if temp < t.end && temp > t.start {
+            return Some(temp);
+        }
+        let temp = (-b + discriminant.sqrt()) / a;
+        if temp < t.end && temp > t.start {
+            return Some(temp);
+        }
+    }
+    None
+}
+
+fn record<T: cgmath::BaseFloat, R>(
+    center: cgmath::Vector3<T>,
+    radius: T,
+    material: &Rc<dyn Material<T, R> + Send + Sync>,
+    ray: &Ray<T>,
+    t: T,
+) -> HitRecord<T, R> {
+    let p = ray.point_at_parameter(t);
+    let normal = (p - center) / radius;
+    let mut rec = HitRecord::new(t, p, normal, sphere_uv(&normal), Rc::clone(material));
+    rec.set_face_normal(ray, normal);
+    rec
+}
+
+fn bounds<T: cgmath::BaseFloat>(center: cgmath::Vector3<T>, radius: T) -> Aabb<T> {
+    let r = vec3(radius, radius, radius);
+    Aabb::new(center - r, center + r)
+}
+
+impl<T: cgmath::BaseFloat, R> Sphere<T, R> {
     pub fn hit_record(&self, ray: &Ray<T>, t: T) -> HitRecord<T, R> {
-        let p = ray.point_at_parameter(t);
-        let normal = (p - self.center) / self.radius;
-        HitRecord::new(t, p, normal, Rc::clone(&self.material))
-    }
-}
-
-impl<T: cgmath::BaseFloat, R> HitTable<T, R> for Sphere<T, R> {
-    fn hit(&self, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
-        let oc = r.origin() - self.center;
-        let a = r.direction().magnitude2();
-        let b = oc.dot(*r.direction());
-        let c = oc.magnitude2() - self.radius * self.radius;
-        let discriminant = b * b - a * c;
-        // todo: making 0 a constant would be an improvement https://github.com/rust-num/num-traits/issues/54
-        if discriminant > T::zero() {
-            let temp = (-b - discriminant.sqrt()) / a;
-            if temp < t.end && temp > t.start {
-                return Some(self.hit_record(r, temp));
-            }
-            let temp = (-b + discriminant.sqrt()) / a;
-            if temp < t.end && temp > t.start {
-                return Some(self.hit_record(r, temp));
-            }
+        record(self.center, self.radius, &self.material, ray, t)
+    }
+}
+
+// a direction in the cone around +z that a sphere of `radius`, `distance2` squared away, fills
+fn random_to_sphere<T, R>(rng: &mut R, radius: T, distance2: T) -> cgmath::Vector3<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let r1 = rng.gen::<T>();
+    let r2 = rng.gen::<T>();
+    let cos_theta_max = (T::one() - radius * radius / distance2)
+        .max(T::zero())
+        .sqrt();
+    let z = T::one() + r2 * (cos_theta_max - T::one());
+    let phi = (pi + pi) * r1;
+    let sin_theta = (T::one() - z * z).max(T::zero()).sqrt();
+    vec3(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+}
+
+// uniform over the cone the sphere fills, which is all that can be seen of it from outside
+fn cone_pdf<T: cgmath::BaseFloat>(
+    center: cgmath::Vector3<T>,
+    radius: T,
+    origin: &cgmath::Vector3<T>,
+    direction: &cgmath::Vector3<T>,
+) -> T {
+    let ray = Ray::new(*origin, *direction, T::zero());
+    let distance2 = (center - origin).magnitude2();
+    let radius2 = radius * radius;
+    if distance2 <= radius2 || intersect(center, radius, &ray, T::zero()..T::infinity()).is_none() {
+        return T::zero();
+    }
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let cos_theta_max = (T::one() - radius2 / distance2).sqrt();
+    T::one() / ((pi + pi) * (T::one() - cos_theta_max))
+}
+
+fn toward_sphere<T, R>(
+    rng: &mut R,
+    center: cgmath::Vector3<T>,
+    radius: T,
+    origin: &cgmath::Vector3<T>,
+) -> cgmath::Vector3<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    let direction = center - origin;
+    let uvw = Onb::from_w(&direction);
+    uvw.local(&random_to_sphere(rng, radius, direction.magnitude2()))
+}
+
+impl<T, R> HitTable<T, R> for Sphere<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        intersect(self.center, self.radius, r, t).map(|t| self.hit_record(r, t))
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(bounds(self.center, self.radius))
+    }
+
+    fn pdf_value(
+        &self,
+        _rng: &mut R,
+        origin: &cgmath::Vector3<T>,
+        direction: &cgmath::Vector3<T>,
+    ) -> T {
+        cone_pdf(self.center, self.radius, origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &cgmath::Vector3<T>) -> cgmath::Vector3<T> {
+        toward_sphere(rng, self.center, self.radius, origin)
+    }
+}
+
+/// A sphere moving in a straight line, from `center0` at `time0` to `center1` at `time1`.
+pub struct MovingSphere<T, R> {
+    center0: cgmath::Vector3<T>,
+    center1: cgmath::Vector3<T>,
+    time0: T,
+    time1: T,
+    radius: T,
+    material: Rc<dyn Material<T, R> + Send + Sync>,
+}
+
+impl<T, R> MovingSphere<T, R> {
+    pub fn new(
+        center0: cgmath::Vector3<T>,
+        center1: cgmath::Vector3<T>,
+        time0: T,
+        time1: T,
+        radius: T,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
         }
-        None
+    }
+}
+
+impl<T: cgmath::BaseFloat, R> MovingSphere<T, R> {
+    /// Where the sphere is at `time`. It keeps going outside of `time0..time1`.
+    pub fn center(&self, time: T) -> cgmath::Vector3<T> {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        let s = (time - self.time0) / (self.time1 - self.time0);
+        self.center0 + (self.center1 - self.center0) * s
+    }
+
+    pub fn hit_record(&self, ray: &Ray<T>, t: T) -> HitRecord<T, R> {
+        record(self.center(ray.time()), self.radius, &self.material, ray, t)
+    }
+
+    // the sphere around everywhere it goes from `time0` to `time1`
+    fn swept_bounds(&self) -> (cgmath::Vector3<T>, T) {
+        let two = T::one() + T::one();
+        let center = (self.center0 + self.center1) / two;
+        let radius = self.radius + (self.center1 - self.center0).magnitude() / two;
+        (center, radius)
+    }
+}
+
+// lights are sampled without knowing the time, so they aim at everywhere the sphere goes.
+// directions that miss it at the ray's time are wasted, but not wrong
+impl<T, R> HitTable<T, R> for MovingSphere<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        intersect(self.center(r.time()), self.radius, r, t).map(|t| self.hit_record(r, t))
+    }
+
+    // the motion is linear so the ends of the range bound everything in between
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        let start = bounds(self.center(time.start), self.radius);
+        Some(start.surrounding(&bounds(self.center(time.end), self.radius)))
+    }
+
+    fn pdf_value(
+        &self,
+        _rng: &mut R,
+        origin: &cgmath::Vector3<T>,
+        direction: &cgmath::Vector3<T>,
+    ) -> T {
+        let (center, radius) = self.swept_bounds();
+        cone_pdf(center, radius, origin, direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &cgmath::Vector3<T>) -> cgmath::Vector3<T> {
+        let (center, radius) = self.swept_bounds();
+        toward_sphere(rng, center, radius, origin)
     }
 }
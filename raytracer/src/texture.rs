@@ -0,0 +1,242 @@
+use cgmath::{vec3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc as Rc;
+
+pub trait Texture<T> {
+    /// The color at surface coordinates `u`, `v` and point `p`.
+    fn value(&self, u: T, v: T, p: &Vector3<T>) -> Vector3<T>;
+}
+
+pub struct SolidColor<T> {
+    color: Vector3<T>,
+}
+
+impl<T> SolidColor<T> {
+    pub fn new(color: Vector3<T>) -> Self {
+        Self { color }
+    }
+}
+
+impl<T: Copy> Texture<T> for SolidColor<T> {
+    fn value(&self, _u: T, _v: T, _p: &Vector3<T>) -> Vector3<T> {
+        self.color
+    }
+}
+
+/// A checkerboard in space, so solids look like they were carved out of it.
+pub struct Checker<T> {
+    odd: Rc<dyn Texture<T> + Send + Sync>,
+    even: Rc<dyn Texture<T> + Send + Sync>,
+    scale: T,
+}
+
+impl<T> Checker<T> {
+    /// Cells are `pi / scale` wide.
+    pub fn new(
+        odd: Rc<dyn Texture<T> + Send + Sync>,
+        even: Rc<dyn Texture<T> + Send + Sync>,
+        scale: T,
+    ) -> Self {
+        Self { odd, even, scale }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for Checker<T> {
+    fn value(&self, u: T, v: T, p: &Vector3<T>) -> Vector3<T> {
+        let sines = (self.scale * p.x).sin() * (self.scale * p.y).sin() * (self.scale * p.z).sin();
+        if sines < T::zero() {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
+
+/// A checkerboard in surface coordinates, `columns` cells across and `rows` cells up.
+pub struct UvChecker<T> {
+    odd: Rc<dyn Texture<T> + Send + Sync>,
+    even: Rc<dyn Texture<T> + Send + Sync>,
+    columns: T,
+    rows: T,
+}
+
+impl<T> UvChecker<T> {
+    pub fn new(
+        odd: Rc<dyn Texture<T> + Send + Sync>,
+        even: Rc<dyn Texture<T> + Send + Sync>,
+        columns: T,
+        rows: T,
+    ) -> Self {
+        Self {
+            odd,
+            even,
+            columns,
+            rows,
+        }
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for UvChecker<T> {
+    fn value(&self, u: T, v: T, p: &Vector3<T>) -> Vector3<T> {
+        let cell = (u * self.columns).floor() + (v * self.rows).floor();
+        let two = T::one() + T::one();
+        if cell % two == T::zero() {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// How texels are blended when an image texture is sampled between them.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// What an image texture shows outside the unit square.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    /// Stretches the edge texels.
+    Clamp,
+    /// Repeats, flipping every other copy.
+    Mirror,
+}
+
+impl Wrap {
+    fn index(self, i: i64, len: usize) -> usize {
+        let len = len as i64;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(len),
+            Wrap::Clamp => i.max(0).min(len - 1),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * len);
+                if i < len {
+                    i
+                } else {
+                    2 * len - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+// the srgb transfer function undone, for channels from 0 to 1
+pub(crate) fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// An image mapped onto the unit square of surface coordinates, with `v` pointing up.
+///
+/// Texels are linear colors, stored in rows from top to bottom. Sampling is bilinear and repeats
+/// unless set otherwise.
+pub struct ImageTexture<T> {
+    width: usize,
+    height: usize,
+    texels: Vec<Vector3<T>>,
+    filter: Filter,
+    wrap: [Wrap; 2],
+}
+
+impl<T> ImageTexture<T> {
+    pub fn new(width: usize, height: usize, texels: Vec<Vector3<T>>) -> Self {
+        assert_eq!(width * height, texels.len(), "texel count mismatch");
+        assert!(!texels.is_empty(), "image textures can't be empty");
+        Self {
+            width,
+            height,
+            texels,
+            filter: Filter::default(),
+            wrap: [Wrap::default(); 2],
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(self, wrap: Wrap) -> Self {
+        self.with_wraps(wrap, wrap)
+    }
+
+    /// Wraps `u` and `v` differently.
+    pub fn with_wraps(mut self, u: Wrap, v: Wrap) -> Self {
+        self.wrap = [u, v];
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn texel(&self, x: i64, y: i64) -> &Vector3<T> {
+        let x = self.wrap[0].index(x, self.width);
+        let y = self.wrap[1].index(y, self.height);
+        &self.texels[x + self.width * y]
+    }
+}
+
+impl<T: cgmath::BaseFloat> ImageTexture<T> {
+    /// Loads a PNG, JPEG or PPM file, treating it as sRGB.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> image::ImageResult<Self> {
+        Ok(Self::from_image(image::open(path)?))
+    }
+
+    /// Decodes an in memory PNG, JPEG or PPM file, treating it as sRGB.
+    pub fn from_bytes(bytes: &[u8]) -> image::ImageResult<Self> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?))
+    }
+
+    fn from_image(image: image::DynamicImage) -> Self {
+        let image = image.to_rgb8();
+        let lut = (0..=255u8)
+            .map(|c| T::from(srgb_to_linear(f64::from(c) / 255.0)).unwrap())
+            .collect::<Vec<_>>();
+        let texels = image
+            .pixels()
+            .map(|p| vec3(lut[p[0] as usize], lut[p[1] as usize], lut[p[2] as usize]))
+            .collect();
+        Self::new(image.width() as usize, image.height() as usize, texels)
+    }
+}
+
+impl<T: cgmath::BaseFloat> Texture<T> for ImageTexture<T> {
+    fn value(&self, u: T, v: T, _p: &Vector3<T>) -> Vector3<T> {
+        // continuous texel coordinates, flipped so rows count down
+        let x = u * T::from(self.width).unwrap();
+        let y = (T::one() - v) * T::from(self.height).unwrap();
+        match self.filter {
+            Filter::Nearest => *self.texel(
+                x.floor().to_i64().unwrap_or(0),
+                y.floor().to_i64().unwrap_or(0),
+            ),
+            Filter::Bilinear => {
+                // texel centers sit at half coordinates
+                let half = T::from(0.5).unwrap();
+                let (x, y) = (x - half, y - half);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0.to_i64().unwrap_or(0), y0.to_i64().unwrap_or(0));
+                let top = self.texel(x0, y0) * (T::one() - fx) + self.texel(x0 + 1, y0) * fx;
+                let bottom =
+                    self.texel(x0, y0 + 1) * (T::one() - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+                top * (T::one() - fy) + bottom * fy
+            }
+        }
+    }
+}
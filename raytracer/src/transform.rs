@@ -0,0 +1,254 @@
+//! Wrappers that place an object somewhere else in the world without rebuilding it.
+//!
+//! Each one moves incoming rays into the object's space and moves hits back out, so a shared
+//! object can be instanced any number of times.
+
+use super::{Aabb, HitRecord, HitTable, Ray};
+use cgmath::{vec3, InnerSpace, Matrix, Matrix4, Rad, SquareMatrix, Vector3};
+use std::ops::Range;
+use std::sync::Arc as Rc;
+
+// the box around the eight corners of `b` after mapping them through `f`
+fn transform_box<T, F>(b: &Aabb<T>, f: F) -> Aabb<T>
+where
+    T: cgmath::BaseFloat,
+    F: Fn(Vector3<T>) -> Vector3<T>,
+{
+    let (min, max) = (b.min(), b.max());
+    let mut corners = (0..8).map(|i| {
+        f(vec3(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        ))
+    });
+    let first = corners.next().unwrap();
+    corners.fold(Aabb::new(first, first), |acc, c| {
+        acc.surrounding(&Aabb::new(c, c))
+    })
+}
+
+pub struct Translate<T, R> {
+    object: Rc<dyn HitTable<T, R> + Send + Sync>,
+    offset: Vector3<T>,
+}
+
+impl<T, R> Translate<T, R> {
+    pub fn new(object: Rc<dyn HitTable<T, R> + Send + Sync>, offset: Vector3<T>) -> Self {
+        Self { object, offset }
+    }
+}
+
+impl<T: cgmath::BaseFloat, R> HitTable<T, R> for Translate<T, R> {
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let moved = Ray::new(r.origin() - self.offset, *r.direction(), r.time());
+        self.object.hit(rng, &moved, t).map(|mut rec| {
+            rec.set_p(rec.get_p() + self.offset);
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        self.object
+            .bounding_box(time)
+            .map(|b| Aabb::new(b.min() + self.offset, b.max() + self.offset))
+    }
+
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        self.object
+            .pdf_value(rng, &(origin - self.offset), direction)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.object.random(rng, &(origin - self.offset))
+    }
+}
+
+/// Turns an object about the y axis, by the right hand rule.
+pub struct RotateY<T, R> {
+    object: Rc<dyn HitTable<T, R> + Send + Sync>,
+    sin: T,
+    cos: T,
+}
+
+impl<T: cgmath::BaseFloat, R> RotateY<T, R> {
+    pub fn new<A: Into<Rad<T>>>(object: Rc<dyn HitTable<T, R> + Send + Sync>, angle: A) -> Self {
+        let Rad(theta) = angle.into();
+        Self {
+            object,
+            sin: theta.sin(),
+            cos: theta.cos(),
+        }
+    }
+
+    fn to_world(&self, v: Vector3<T>) -> Vector3<T> {
+        vec3(
+            self.cos * v.x + self.sin * v.z,
+            v.y,
+            -self.sin * v.x + self.cos * v.z,
+        )
+    }
+
+    fn to_object(&self, v: Vector3<T>) -> Vector3<T> {
+        vec3(
+            self.cos * v.x - self.sin * v.z,
+            v.y,
+            self.sin * v.x + self.cos * v.z,
+        )
+    }
+}
+
+impl<T: cgmath::BaseFloat, R> HitTable<T, R> for RotateY<T, R> {
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let rotated = Ray::new(
+            self.to_object(*r.origin()),
+            self.to_object(*r.direction()),
+            r.time(),
+        );
+        self.object.hit(rng, &rotated, t).map(|mut rec| {
+            rec.set_p(self.to_world(*rec.get_p()));
+            rec.set_normal(self.to_world(*rec.get_normal()));
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        self.object
+            .bounding_box(time)
+            .map(|b| transform_box(&b, |c| self.to_world(c)))
+    }
+
+    // rotations don't change solid angles, so densities carry over as they are
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        self.object
+            .pdf_value(rng, &self.to_object(*origin), &self.to_object(*direction))
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        self.to_world(self.object.random(rng, &self.to_object(*origin)))
+    }
+}
+
+/// Applies an arbitrary affine matrix: any mix of scaling, rotation, shearing and translation.
+pub struct Transform<T, R> {
+    object: Rc<dyn HitTable<T, R> + Send + Sync>,
+    matrix: Matrix4<T>,
+    inverse: Matrix4<T>,
+}
+
+impl<T: cgmath::BaseFloat, R> Transform<T, R> {
+    /// `None` if `matrix` can't be inverted.
+    pub fn new(object: Rc<dyn HitTable<T, R> + Send + Sync>, matrix: Matrix4<T>) -> Option<Self> {
+        let inverse = matrix.invert()?;
+        Some(Self {
+            object,
+            matrix,
+            inverse,
+        })
+    }
+
+    pub fn matrix(&self) -> &Matrix4<T> {
+        &self.matrix
+    }
+}
+
+impl<T: cgmath::BaseFloat, R> HitTable<T, R> for Transform<T, R> {
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        // the direction isn't renormalized, so `t` means the same thing in both spaces
+        let transformed = Ray::new(
+            (self.inverse * r.origin().extend(T::one())).truncate(),
+            (self.inverse * r.direction().extend(T::zero())).truncate(),
+            r.time(),
+        );
+        self.object.hit(rng, &transformed, t).map(|mut rec| {
+            rec.set_p((self.matrix * rec.get_p().extend(T::one())).truncate());
+            // normals go through the inverse transpose to stay perpendicular under scaling. it
+            // also keeps them facing the ray, so the front face doesn't change
+            let normal = self.inverse.transpose() * rec.get_normal().extend(T::zero());
+            rec.set_normal(normal.truncate().normalize());
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        self.object
+            .bounding_box(time)
+            .map(|b| transform_box(&b, |c| (self.matrix * c.extend(T::one())).truncate()))
+    }
+
+    // the matrix stretches solid angles unevenly, so the density of a direction is scaled by
+    // how much the inverse squeezes the directions around it: |det A| / |A w|^3 for the unit
+    // direction w, where A is the inverse
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        let inner = (self.inverse * direction.extend(T::zero())).truncate();
+        let pdf = self.object.pdf_value(
+            rng,
+            &(self.inverse * origin.extend(T::one())).truncate(),
+            &inner,
+        );
+        let stretch = inner.magnitude() / direction.magnitude();
+        pdf * self.inverse.determinant().abs() / (stretch * stretch * stretch)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        let origin = (self.inverse * origin.extend(T::one())).truncate();
+        let direction = self.object.random(rng, &origin);
+        (self.matrix * direction.extend(T::zero())).truncate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiffuseLight, Sphere};
+    use cgmath::Deg;
+    use rand::prelude::*;
+    use rand::rngs::SmallRng;
+
+    type Object = Rc<dyn HitTable<f64, SmallRng> + Send + Sync>;
+
+    fn lamp() -> Object {
+        let light = Rc::new(DiffuseLight::new(vec3(1.0, 1.0, 1.0)));
+        Rc::new(Sphere::new(vec3(0.0, 0.0, 0.0), 1.0, light))
+    }
+
+    // a light's density should integrate to one over every direction seen from `origin`, and
+    // cover every direction it samples
+    fn assert_sampled_consistently(light: &dyn HitTable<f64, SmallRng>, origin: Vector3<f64>) {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 400_000;
+        let four_pi = 4.0 * std::f64::consts::PI;
+        let integral = (0..n)
+            .map(|_| {
+                // uniform over the sphere of directions
+                let z = 1.0 - 2.0 * rng.gen::<f64>();
+                let phi = four_pi / 2.0 * rng.gen::<f64>();
+                let ring = (1.0 - z * z).sqrt();
+                let direction = vec3(ring * phi.cos(), ring * phi.sin(), z);
+                light.pdf_value(&mut rng, &origin, &direction)
+            })
+            .sum::<f64>()
+            * four_pi
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.05, "integrates to {}", integral);
+        for _ in 0..1000 {
+            let direction = light.random(&mut rng, &origin);
+            assert!(light.pdf_value(&mut rng, &origin, &direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn wrapped_lights_sample_consistently() {
+        let origin = vec3(0.5, -1.0, 4.0);
+        let translated = Translate::new(lamp(), vec3(0.0, 1.0, 1.0));
+        assert_sampled_consistently(&translated, origin);
+        let rotated = RotateY::new(lamp(), Deg(30.0));
+        assert_sampled_consistently(&rotated, origin);
+        // stretched unevenly and sheared, which changes solid angles
+        let matrix = Matrix4::new(
+            2.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0, 1.5, 0.0, 0.0, 1.0, 0.0, 1.0,
+        );
+        let transformed = Transform::new(lamp(), matrix).unwrap();
+        assert_sampled_consistently(&transformed, origin);
+    }
+}
@@ -0,0 +1,476 @@
+use super::{Aabb, Bvh, HitRecord, HitTable, Material, Ray};
+use cgmath::{vec2, vec3, InnerSpace, Vector2, Vector3};
+use rand::distributions::Standard;
+use rand::prelude::*;
+use std::ops::Range;
+use std::sync::Arc as Rc;
+
+/// Möller–Trumbore. Returns the ray parameter and the barycentric coordinates of `v1` and `v2`.
+fn intersect<T: cgmath::BaseFloat>(
+    r: &Ray<T>,
+    [v0, v1, v2]: [&Vector3<T>; 3],
+    t: Range<T>,
+) -> Option<(T, T, T)> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+    let pvec = r.direction().cross(e2);
+    let det = e1.dot(pvec);
+    if det == T::zero() {
+        return None;
+    }
+    let inv_det = T::one() / det;
+
+    let tvec = r.origin() - v0;
+    let u = tvec.dot(pvec) * inv_det;
+    if u < T::zero() || u > T::one() {
+        return None;
+    }
+
+    let qvec = tvec.cross(e1);
+    let v = r.direction().dot(qvec) * inv_det;
+    if v < T::zero() || u + v > T::one() {
+        return None;
+    }
+
+    let temp = e2.dot(qvec) * inv_det;
+    if temp < t.end && temp > t.start {
+        Some((temp, u, v))
+    } else {
+        None
+    }
+}
+
+// twice the area, facing the side the vertices wind counter-clockwise around
+fn area_vector<T: cgmath::BaseFloat>([v0, v1, v2]: [&Vector3<T>; 3]) -> Vector3<T> {
+    (v1 - v0).cross(v2 - v0)
+}
+
+// the solid angle density of `direction` from `origin` for points picked uniformly over `area`,
+// if it crosses the triangle
+fn solid_angle_pdf<T: cgmath::BaseFloat>(
+    vertices: [&Vector3<T>; 3],
+    origin: &Vector3<T>,
+    direction: &Vector3<T>,
+    area: T,
+) -> T {
+    let ray = Ray::new(*origin, *direction, T::zero());
+    match intersect(&ray, vertices, T::zero()..T::infinity()) {
+        None => T::zero(),
+        Some((t, _, _)) => {
+            let length2 = direction.magnitude2();
+            let distance2 = t * t * length2;
+            let cosine = direction.dot(area_vector(vertices).normalize()).abs() / length2.sqrt();
+            // seen edge on, the triangle covers no solid angle
+            if area * cosine > T::zero() {
+                distance2 / (cosine * area)
+            } else {
+                T::zero()
+            }
+        }
+    }
+}
+
+// a uniformly random point on the triangle
+fn random_point<T, R>(rng: &mut R, [v0, v1, v2]: [&Vector3<T>; 3]) -> Vector3<T>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    let (mut u, mut v) = (rng.gen::<T>(), rng.gen::<T>());
+    // fold the far half of the parallelogram back onto the triangle
+    if u + v > T::one() {
+        u = T::one() - u;
+        v = T::one() - v;
+    }
+    v0 + (v1 - v0) * u + (v2 - v0) * v
+}
+
+fn bounds<T: cgmath::BaseFloat>([v0, v1, v2]: [&Vector3<T>; 3]) -> Aabb<T> {
+    Aabb::new(
+        vec3(
+            v0.x.min(v1.x).min(v2.x),
+            v0.y.min(v1.y).min(v2.y),
+            v0.z.min(v1.z).min(v2.z),
+        ),
+        vec3(
+            v0.x.max(v1.x).max(v2.x),
+            v0.y.max(v1.y).max(v2.y),
+            v0.z.max(v1.z).max(v2.z),
+        ),
+    )
+}
+
+/// A single triangle. The normal faces the side the vertices wind counter-clockwise around.
+pub struct Triangle<T, R> {
+    vertices: [Vector3<T>; 3],
+    normal: Vector3<T>,
+    material: Rc<dyn Material<T, R> + Send + Sync>,
+}
+
+impl<T: cgmath::BaseFloat, R> Triangle<T, R> {
+    /// `None` if the vertices are collinear, leaving the triangle without an area or a normal.
+    pub fn new(
+        v0: Vector3<T>,
+        v1: Vector3<T>,
+        v2: Vector3<T>,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Option<Self> {
+        let area = area_vector([&v0, &v1, &v2]);
+        if area.magnitude2() > T::zero() {
+            Some(Self {
+                vertices: [v0, v1, v2],
+                normal: area.normalize(),
+                material,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, R> HitTable<T, R> for Triangle<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let [v0, v1, v2] = &self.vertices;
+        // the barycentric coordinates double as surface coordinates
+        intersect(r, [v0, v1, v2], t).map(|(t, u, v)| {
+            let mut rec = HitRecord::new(
+                t,
+                r.point_at_parameter(t),
+                self.normal,
+                vec2(u, v),
+                Rc::clone(&self.material),
+            );
+            rec.set_face_normal(r, self.normal);
+            rec
+        })
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        let [v0, v1, v2] = &self.vertices;
+        Some(bounds([v0, v1, v2]))
+    }
+
+    // uniform over the triangle's area, converted to solid angle
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        let [v0, v1, v2] = &self.vertices;
+        let area = area_vector([v0, v1, v2]).magnitude() / T::from(2.0).unwrap();
+        solid_angle_pdf([v0, v1, v2], origin, direction, area)
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        let [v0, v1, v2] = &self.vertices;
+        random_point(rng, [v0, v1, v2]) - origin
+    }
+}
+
+struct MeshData<T, R> {
+    positions: Vec<Vector3<T>>,
+    normals: Option<Vec<Vector3<T>>>,
+    uvs: Option<Vec<Vector2<T>>>,
+    indices: Vec<[usize; 3]>,
+    material: Rc<dyn Material<T, R> + Send + Sync>,
+}
+
+impl<T, R> MeshData<T, R> {
+    fn vertices(&self, face: usize) -> [&Vector3<T>; 3] {
+        let [i0, i1, i2] = self.indices[face];
+        [
+            &self.positions[i0],
+            &self.positions[i1],
+            &self.positions[i2],
+        ]
+    }
+}
+
+// one face of a mesh, so that each face can be a leaf of the mesh's bvh
+struct MeshTriangle<T, R> {
+    mesh: Rc<MeshData<T, R>>,
+    face: usize,
+    // of the whole mesh, which points are picked uniformly over
+    mesh_area: T,
+}
+
+impl<T: cgmath::BaseFloat, R> HitTable<T, R> for MeshTriangle<T, R> {
+    fn hit(&self, _rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        let vertices = self.mesh.vertices(self.face);
+        intersect(r, vertices, t).map(|(t, u, v)| {
+            let [i0, i1, i2] = self.mesh.indices[self.face];
+            let w = T::one() - u - v;
+            let normal = match &self.mesh.normals {
+                Some(normals) => normals[i0] * w + normals[i1] * u + normals[i2] * v,
+                None => area_vector(vertices),
+            };
+            // vertex normals pointing opposite ways can cancel out, so fall back to the face's
+            let normal = if normal.magnitude2() > T::epsilon() {
+                normal.normalize()
+            } else {
+                area_vector(vertices).normalize()
+            };
+            let uv = match &self.mesh.uvs {
+                Some(uvs) => uvs[i0] * w + uvs[i1] * u + uvs[i2] * v,
+                None => vec2(u, v),
+            };
+            let mut rec = HitRecord::new(
+                t,
+                r.point_at_parameter(t),
+                normal,
+                uv,
+                Rc::clone(&self.mesh.material),
+            );
+            rec.set_face_normal(r, normal);
+            rec
+        })
+    }
+
+    fn bounding_box(&self, _time: Range<T>) -> Option<Aabb<T>> {
+        Some(bounds(self.mesh.vertices(self.face)))
+    }
+
+    fn pdf_value(&self, _rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        solid_angle_pdf(
+            self.mesh.vertices(self.face),
+            origin,
+            direction,
+            self.mesh_area,
+        )
+    }
+}
+
+/// An indexed triangle mesh with optional per-vertex normals and texture coordinates.
+///
+/// Without normals the mesh is flat shaded with the winding order normal of each face. Faces with
+/// collinear vertices can't be hit, and are left out.
+pub struct TriangleMesh<T, R> {
+    mesh: Rc<MeshData<T, R>>,
+    bvh: Bvh<T, R>,
+    // the area of every face up to and including each one, to pick faces by area
+    areas: Vec<T>,
+}
+
+impl<T, R> TriangleMesh<T, R>
+where
+    T: cgmath::BaseFloat + Send + Sync + 'static,
+    R: 'static,
+{
+    /// Panics if an index is out of bounds or if `normals` or `uvs` don't have an entry for every
+    /// position.
+    pub fn new(
+        positions: Vec<Vector3<T>>,
+        normals: Option<Vec<Vector3<T>>>,
+        uvs: Option<Vec<Vector2<T>>>,
+        indices: Vec<[usize; 3]>,
+        material: Rc<dyn Material<T, R> + Send + Sync>,
+    ) -> Self {
+        assert!(
+            indices.iter().flatten().all(|i| *i < positions.len()),
+            "mesh index out of bounds"
+        );
+        if let Some(normals) = &normals {
+            assert_eq!(normals.len(), positions.len(), "normal count mismatch");
+        }
+        if let Some(uvs) = &uvs {
+            assert_eq!(uvs.len(), positions.len(), "uv count mismatch");
+        }
+
+        let mesh = Rc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            indices,
+            material,
+        });
+        let two = T::one() + T::one();
+        let areas = (0..mesh.indices.len())
+            .scan(T::zero(), |total, face| {
+                *total += area_vector(mesh.vertices(face)).magnitude() / two;
+                Some(*total)
+            })
+            .collect::<Vec<_>>();
+        let mesh_area = areas.last().copied().unwrap_or_else(T::zero);
+        let faces = (0..mesh.indices.len())
+            .filter(|face| area_vector(mesh.vertices(*face)).magnitude2() > T::zero())
+            .map(|face| {
+                Box::new(MeshTriangle {
+                    mesh: Rc::clone(&mesh),
+                    face,
+                    mesh_area,
+                }) as Box<dyn HitTable<T, R> + Send + Sync>
+            })
+            .collect();
+        // meshes don't move so the shutter interval doesn't matter
+        let bvh = Bvh::new(faces, T::zero()..T::one());
+        Self { mesh, bvh, areas }
+    }
+}
+
+impl<T, R> TriangleMesh<T, R> {
+    pub fn positions(&self) -> &[Vector3<T>] {
+        &self.mesh.positions
+    }
+
+    pub fn normals(&self) -> Option<&[Vector3<T>]> {
+        self.mesh.normals.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[Vector2<T>]> {
+        self.mesh.uvs.as_deref()
+    }
+
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.mesh.indices
+    }
+
+    pub fn material(&self) -> &Rc<dyn Material<T, R> + Send + Sync> {
+        &self.mesh.material
+    }
+}
+
+impl<T, R> HitTable<T, R> for TriangleMesh<T, R>
+where
+    T: cgmath::BaseFloat,
+    Standard: Distribution<T>,
+    R: rand::Rng,
+{
+    fn hit(&self, rng: &mut R, r: &Ray<T>, t: Range<T>) -> Option<HitRecord<T, R>> {
+        self.bvh.hit(rng, r, t)
+    }
+
+    fn bounding_box(&self, time: Range<T>) -> Option<Aabb<T>> {
+        self.bvh.bounding_box(time)
+    }
+
+    // uniform over the whole surface. any face `direction` crosses could have been picked, so
+    // the density sums over all of them, and the bvh narrows those down to the faces near the ray
+    fn pdf_value(&self, rng: &mut R, origin: &Vector3<T>, direction: &Vector3<T>) -> T {
+        let ray = Ray::new(*origin, *direction, T::zero());
+        let mut pdf = T::zero();
+        self.bvh
+            .for_each_crossed(&ray, T::zero()..T::infinity(), |face| {
+                pdf += face.pdf_value(rng, origin, direction)
+            });
+        pdf
+    }
+
+    fn random(&self, rng: &mut R, origin: &Vector3<T>) -> Vector3<T> {
+        let total = match self.areas.last() {
+            Some(total) if *total > T::zero() => *total,
+            _ => return vec3(T::one(), T::zero(), T::zero()),
+        };
+        let x = rng.gen::<T>() * total;
+        let face = self.areas.partition_point(|area| *area <= x);
+        let face = face.min(self.areas.len() - 1);
+        random_point(rng, self.mesh.vertices(face)) - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lambertian;
+    use rand::rngs::SmallRng;
+
+    fn triangle(v2: Vector3<f64>) -> Option<Triangle<f64, SmallRng>> {
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        Triangle::new(vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0), v2, material)
+    }
+
+    #[test]
+    fn collinear_vertices_are_rejected() {
+        assert!(triangle(vec3(2.0, 0.0, 0.0)).is_none());
+        assert!(triangle(vec3(0.0, 0.0, 0.0)).is_none());
+        assert!(triangle(vec3(0.0, 1.0, 0.0)).is_some());
+    }
+
+    #[test]
+    fn pdf_is_finite_edge_on() {
+        let triangle = triangle(vec3(0.0, 1.0, 0.0)).unwrap();
+        let mut rng = SmallRng::seed_from_u64(0);
+        // grazing along the plane of the triangle
+        let pdf = triangle.pdf_value(&mut rng, &vec3(-1.0, 0.25, 0.0), &vec3(1.0, 0.0, 0.0));
+        assert_eq!(pdf, 0.0);
+        let pdf = triangle.pdf_value(&mut rng, &vec3(0.25, 0.25, 1.0), &vec3(0.0, 0.0, -1.0));
+        assert!(pdf.is_finite() && pdf > 0.0);
+    }
+
+    #[test]
+    fn degenerate_mesh_faces_are_skipped() {
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        let positions = vec![
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ];
+        let mesh: TriangleMesh<f64, SmallRng> =
+            TriangleMesh::new(positions, None, None, vec![[0, 1, 2], [0, 1, 3]], material);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let ray = Ray::new(vec3(0.25, 0.25, 1.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&mut rng, &ray, 0.0..f64::MAX).unwrap();
+        assert!(hit.get_normal().x.is_finite());
+        assert_eq!(mesh.indices().len(), 2);
+    }
+
+    #[test]
+    fn cancelling_vertex_normals_fall_back_to_the_face() {
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        let positions = vec![
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ];
+        let s = 0.75f64.sqrt();
+        let normals = vec![vec3(1.0, 0.0, 0.0), vec3(-0.5, s, 0.0), vec3(-0.5, -s, 0.0)];
+        let mesh: TriangleMesh<f64, SmallRng> =
+            TriangleMesh::new(positions, Some(normals), None, vec![[0, 1, 2]], material);
+        let mut rng = SmallRng::seed_from_u64(0);
+        // through the centroid, where the three normals sum to nothing
+        let ray = Ray::new(vec3(1.0 / 3.0, 1.0 / 3.0, 1.0), vec3(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&mut rng, &ray, 0.0..f64::MAX).unwrap();
+        assert!((hit.get_normal() - vec3(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn mesh_pdf_sums_every_crossed_face() {
+        let material = Rc::new(Lambertian::new(vec3(0.5, 0.5, 0.5)));
+        // a stack of grids, so rays cross several faces
+        let n = 8;
+        let mut positions = vec![];
+        let mut indices = vec![];
+        for layer in 0..3 {
+            let base = positions.len();
+            for y in 0..=n {
+                for x in 0..=n {
+                    positions.push(vec3(x as f64, y as f64, layer as f64));
+                }
+            }
+            for y in 0..n {
+                for x in 0..n {
+                    let i = base + x + (n + 1) * y;
+                    indices.push([i, i + 1, i + n + 2]);
+                    indices.push([i, i + n + 2, i + n + 1]);
+                }
+            }
+        }
+        let mesh: TriangleMesh<f64, SmallRng> =
+            TriangleMesh::new(positions, None, None, indices, material);
+        let total = *mesh.areas.last().unwrap();
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let origin = vec3(4.0, 4.0, 5.0);
+        for _ in 0..200 {
+            let direction = mesh.random(&mut rng, &origin);
+            let expected = (0..mesh.indices().len()).fold(0.0, |acc, face| {
+                acc + solid_angle_pdf(mesh.mesh.vertices(face), &origin, &direction, total)
+            });
+            let pdf = mesh.pdf_value(&mut rng, &origin, &direction);
+            assert!(expected > 0.0);
+            assert!((pdf - expected).abs() <= 1e-9 * expected);
+        }
+    }
+}